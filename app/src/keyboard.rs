@@ -1,6 +1,6 @@
-use crossbeam::channel;
 use eframe::egui;
 use log::warn;
+use pistolhot_synth::MessageSender;
 use std::{collections::HashSet, convert::TryFrom};
 use wmidi::MidiMessage;
 
@@ -12,11 +12,11 @@ fn is_key_black(note: wmidi::Note) -> bool {
 
 pub struct OnScreenKeyboard {
     key_pressed: HashSet<egui::Id>,
-    midi_tx: channel::Sender<MidiMessage<'static>>,
+    midi_tx: MessageSender,
 }
 
 impl OnScreenKeyboard {
-    pub fn new(midi_tx: channel::Sender<MidiMessage<'static>>) -> Self {
+    pub fn new(midi_tx: MessageSender) -> Self {
         Self {
             key_pressed: HashSet::new(),
             midi_tx,
@@ -43,20 +43,26 @@ impl OnScreenKeyboard {
                 // egui doesn't seem to have any convenient "pressed" or "released" event
                 if r.is_pointer_button_down_on() {
                     if self.key_pressed.insert(r.id) {
-                        if let Err(e) = self.midi_tx.try_send(MidiMessage::NoteOn(
-                            wmidi::Channel::Ch1,
-                            note,
-                            wmidi::Velocity::from_u8_lossy(127),
-                        )) {
+                        if let Err(e) = self.midi_tx.try_send(
+                            MidiMessage::NoteOn(
+                                wmidi::Channel::Ch1,
+                                note,
+                                wmidi::Velocity::from_u8_lossy(127),
+                            )
+                            .into(),
+                        ) {
                             warn!("error sending note on midi message {}", e);
                         }
                     }
                 } else if self.key_pressed.remove(&r.id) {
-                    if let Err(e) = self.midi_tx.try_send(MidiMessage::NoteOff(
-                        wmidi::Channel::Ch1,
-                        note,
-                        wmidi::Velocity::from_u8_lossy(0),
-                    )) {
+                    if let Err(e) = self.midi_tx.try_send(
+                        MidiMessage::NoteOff(
+                            wmidi::Channel::Ch1,
+                            note,
+                            wmidi::Velocity::from_u8_lossy(0),
+                        )
+                        .into(),
+                    ) {
                         warn!("error sending midi note off message {}", e);
                     }
                 }
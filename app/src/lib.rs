@@ -2,10 +2,14 @@ mod audio;
 mod keyboard;
 mod midi;
 mod periodic_updater;
+mod presets;
+mod settings;
 mod timer;
 use crate::keyboard::OnScreenKeyboard;
 use crate::midi::MidiReader;
 use crate::periodic_updater::PeriodicUpdater;
+use crate::presets::PresetBrowser;
+use crate::settings::Settings;
 use crate::{audio::AudioManager, pistolhot_synth::Params};
 use cpal::traits::DeviceTrait;
 use crossbeam::channel;
@@ -14,7 +18,10 @@ use eframe::{
     epi::{self, App},
 };
 use log::warn;
-use pistolhot_synth::{self, dbg_gui, params_gui, Synth};
+use pistolhot_synth::{
+    self, compare_gui, dbg_gui, params_gui, randomizer_gui, CompareSlots, History, KeyboardMapping,
+    MessageSender, Patch, Programs, Randomizer, Scale, Synth, Tuning, CHANNEL_CAPACITY,
+};
 use std::{collections::VecDeque, sync::Arc};
 
 const NAME: &str = "Pistolhot";
@@ -27,40 +34,160 @@ pub struct Data {
     forced_buffer_size: Option<u32>,
     left_vis_buffer: VecDeque<f32>,
     synth_params: Arc<Params>,
+    synth_sender: MessageSender,
+    synth_programs: Arc<Programs>,
     periodic_updater: Option<PeriodicUpdater>,
+    presets: PresetBrowser,
+    history: History,
+    compare: CompareSlots,
+    randomizer: Randomizer,
+    scale_path: String,
+    keyboard_mapping_path: String,
+}
+
+impl Data {
+    /// anything that is no longer available is left at its default
+    fn restore(&mut self, settings: Settings) {
+        if let Some(name) = settings.audio_device {
+            match self
+                .audio
+                .get_devices()
+                .into_iter()
+                .find(|device| device.name().ok().as_ref() == Some(&name))
+            {
+                Some(device) => self.audio.set_device(device),
+                None => warn!("audio device {name} not found"),
+            }
+        }
+        self.forced_buffer_size = settings.forced_buffer_size;
+        self.audio.set_forced_buffer_size(self.forced_buffer_size);
+        if let Some(port) = settings.midi_port {
+            self.midi.set_port(port);
+        }
+        if let Some(patch) = settings.patch {
+            if let Err(e) =
+                Patch::from_toml(&patch).and_then(|patch| self.synth_params.from_patch(&patch))
+            {
+                warn!("error restoring sound: {e}");
+            }
+        }
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            audio_device: self.audio.get_name(),
+            forced_buffer_size: self.forced_buffer_size,
+            midi_port: self.midi.get_port(),
+            patch: Some(self.synth_params.to_patch().to_toml()),
+        }
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
 pub enum Pistolhot {
     Initialized(Data),
-    Uninitialized,
+    /// holds the settings to restore once initialized
+    Uninitialized(Settings),
 }
 
 impl Pistolhot {
     fn init(&mut self) {
-        let (midi_tx, midi_rx) = channel::bounded(256);
-        let midi = MidiReader::new(midi_tx.clone());
+        let settings = match self {
+            Self::Uninitialized(settings) => std::mem::take(settings),
+            Self::Initialized(_) => return,
+        };
+        let (synth_tx, synth_rx) = channel::bounded(CHANNEL_CAPACITY);
+        let midi = MidiReader::new(synth_tx.clone());
 
-        let mut synth = Some(Synth::new(midi_rx));
+        let mut synth = Some(Synth::new(synth_rx));
         let synth_params = synth.as_ref().unwrap().get_params();
+        let synth_programs = synth.as_ref().unwrap().get_programs();
         let audio = AudioManager::new(synth.take().unwrap(), move |e| {
             warn!("{e}");
         });
         *self = Self::Initialized(Data {
             audio,
             midi,
-            keyboard: OnScreenKeyboard::new(midi_tx),
+            keyboard: OnScreenKeyboard::new(synth_tx.clone()),
             forced_buffer_size: None,
             left_vis_buffer: VecDeque::with_capacity(VIS_SIZE * 2),
             synth_params,
+            synth_sender: synth_tx,
+            synth_programs,
             periodic_updater: None,
+            presets: PresetBrowser::default(),
+            history: History::default(),
+            compare: CompareSlots::default(),
+            randomizer: Randomizer::default(),
+            scale_path: String::new(),
+            keyboard_mapping_path: String::new(),
         });
+        self.restore(settings);
     }
+
+    fn restore(&mut self, settings: Settings) {
+        match self {
+            Self::Initialized(data) => data.restore(settings),
+            Self::Uninitialized(saved) => *saved = settings,
+        }
+    }
+}
+
+fn load_tuning(scale_path: &str, keyboard_mapping_path: &str) -> anyhow::Result<Tuning> {
+    let scale = match scale_path {
+        "" => None,
+        path => Some(Scale::parse(&std::fs::read_to_string(path)?)?),
+    };
+    let keyboard_mapping = match keyboard_mapping_path {
+        "" => None,
+        path => Some(KeyboardMapping::parse(&std::fs::read_to_string(path)?)?),
+    };
+    Ok(Tuning {
+        scale,
+        keyboard_mapping,
+    })
+}
+
+fn tuning_gui(
+    ui: &mut egui::Ui,
+    params: &Params,
+    scale_path: &mut String,
+    keyboard_mapping_path: &mut String,
+) {
+    ui.horizontal(|ui| {
+        ui.label("tuning:");
+        ui.label(
+            params
+                .get_tuning()
+                .scale
+                .map(|scale| scale.description)
+                .unwrap_or_else(|| "12-TET".to_string()),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("scl:");
+        ui.text_edit_singleline(scale_path);
+    });
+    ui.horizontal(|ui| {
+        ui.label("kbm:");
+        ui.text_edit_singleline(keyboard_mapping_path);
+    });
+    ui.horizontal(|ui| {
+        if ui.button("load").clicked() {
+            match load_tuning(scale_path, keyboard_mapping_path) {
+                Ok(tuning) => params.set_tuning(tuning),
+                Err(e) => warn!("error loading tuning: {e}"),
+            }
+        }
+        if ui.button("reset").clicked() {
+            params.set_tuning(Tuning::default());
+        }
+    });
 }
 
 impl Default for Pistolhot {
     fn default() -> Self {
-        let mut s = Self::Uninitialized;
+        let mut s = Self::Uninitialized(Settings::default());
         // need to defer initializion in wasm due to chrome's autoplay blocking and such
         if cfg!(not(target_arch = "wasm32")) {
             s.init();
@@ -74,6 +201,25 @@ impl App for Pistolhot {
         NAME
     }
 
+    fn setup(
+        &mut self,
+        _ctx: &egui::Context,
+        _frame: &epi::Frame,
+        storage: Option<&dyn epi::Storage>,
+    ) {
+        if let Some(storage) = storage {
+            self.restore(Settings::load(storage));
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn epi::Storage) {
+        match self {
+            Self::Initialized(data) => data.settings().save(storage),
+            // keep what was restored if the user never started the synth
+            Self::Uninitialized(settings) => settings.save(storage),
+        }
+    }
+
     fn on_exit(&mut self) {
         if let Self::Initialized(Data {
             periodic_updater, ..
@@ -87,7 +233,7 @@ impl App for Pistolhot {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading(NAME);
             match self {
-                Self::Uninitialized => {
+                Self::Uninitialized(_) => {
                     if ui.button("start").clicked() {
                         self.init();
                     }
@@ -104,116 +250,179 @@ impl App for Pistolhot {
                     let forced_buffer_size = &mut data.forced_buffer_size;
                     let keyboard = &mut data.keyboard;
                     let params = data.synth_params.as_ref();
-                    ui.group(|ui| {
-                        ui.horizontal(|ui| {
-                            ui.label("midi:");
-                            ui.label(midi.get_name());
-                        });
-                    });
-
-                    ui.group(|ui| {
-                        ui.horizontal(|ui| {
-                            ui.label("audio:");
-                            let mut selected = audio.get_name().unwrap_or_else(|| "-".to_string());
-                            egui::ComboBox::from_id_source("audio combo box")
-                                .selected_text(&selected)
-                                .show_ui(ui, |ui| {
-                                    // TODO cache this to not poll too often
-                                    for device in audio.get_devices() {
-                                        if let Ok(name) = device.name() {
-                                            ui.selectable_value(&mut selected, name.clone(), name);
-                                        }
+                    let synth_sender = &data.synth_sender;
+                    // the tuning of program changes from midi
+                    data.synth_programs.update(params);
+                    let presets = &mut data.presets;
+                    let history = &mut data.history;
+                    let compare = &mut data.compare;
+                    let randomizer = &mut data.randomizer;
+                    // leave ctrl+z to text fields while they have focus
+                    if !ctx.wants_keyboard_input() {
+                        let (undo, redo) = {
+                            let input = ctx.input();
+                            let z = input.modifiers.command && input.key_pressed(egui::Key::Z);
+                            (z && !input.modifiers.shift, z && input.modifiers.shift)
+                        };
+                        if undo {
+                            history.undo(synth_sender);
+                        } else if redo {
+                            history.redo(synth_sender);
+                        }
+                    }
+                    let scale_path = &mut data.scale_path;
+                    let keyboard_mapping_path = &mut data.keyboard_mapping_path;
+                    // the rest of the window, minus the onscreen keyboard at the bottom
+                    egui::ScrollArea::vertical()
+                        .max_height(ui.available_size().y - 20f32)
+                        .show(ui, |ui| {
+                            ui.group(|ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("midi:");
+                                    let current = midi.get_name();
+                                    let mut selected = current.clone();
+                                    egui::ComboBox::from_id_source("midi combo box")
+                                        .selected_text(&selected)
+                                        .show_ui(ui, |ui| {
+                                            for name in midi.get_port_names() {
+                                                ui.selectable_value(
+                                                    &mut selected,
+                                                    name.clone(),
+                                                    name,
+                                                );
+                                            }
+                                        });
+                                    if selected != current {
+                                        midi.set_port(selected);
                                     }
                                 });
-                            if Some(&selected) != audio.get_name().as_ref() {
-                                if let Some(device) = audio.get_devices().into_iter().find(|d| {
-                                    if let Ok(name) = d.name() {
-                                        name == selected
-                                    } else {
-                                        false
-                                    }
-                                }) {
-                                    audio.set_device(device);
-                                }
-                            }
-                        });
-                        let buffer_range = audio.get_buffer_size_range();
-                        ui.horizontal(|ui| {
-                            ui.label("buffer size:");
+                            });
+
                             ui.group(|ui| {
-                                if buffer_range.is_none() {
-                                    ui.set_enabled(false);
-                                    *forced_buffer_size = None;
-                                }
-                                let mut forced = forced_buffer_size.is_some();
                                 ui.horizontal(|ui| {
-                                    ui.checkbox(&mut forced, "force");
-                                    ui.set_enabled(forced);
-                                    let mut size = match forced_buffer_size.to_owned() {
-                                        Some(size) => size,
-                                        None => audio.get_buffer_size().unwrap_or(0),
-                                    };
-                                    let range = match buffer_range {
-                                        // limit max to something sensible
-                                        Some((min, max)) => min..=max.min(16384),
-                                        None => 0..=1,
-                                    };
-                                    ui.add(egui::Slider::new(&mut size, range));
-                                    if forced {
-                                        *forced_buffer_size = Some(size);
-                                    } else {
-                                        *forced_buffer_size = None;
+                                    ui.label("audio:");
+                                    let mut selected =
+                                        audio.get_name().unwrap_or_else(|| "-".to_string());
+                                    egui::ComboBox::from_id_source("audio combo box")
+                                        .selected_text(&selected)
+                                        .show_ui(ui, |ui| {
+                                            // TODO cache this to not poll too often
+                                            for device in audio.get_devices() {
+                                                if let Ok(name) = device.name() {
+                                                    ui.selectable_value(
+                                                        &mut selected,
+                                                        name.clone(),
+                                                        name,
+                                                    );
+                                                }
+                                            }
+                                        });
+                                    if Some(&selected) != audio.get_name().as_ref() {
+                                        if let Some(device) =
+                                            audio.get_devices().into_iter().find(|d| {
+                                                if let Ok(name) = d.name() {
+                                                    name == selected
+                                                } else {
+                                                    false
+                                                }
+                                            })
+                                        {
+                                            audio.set_device(device);
+                                        }
                                     }
-                                    audio.set_forced_buffer_size(*forced_buffer_size);
                                 });
-                            });
-                        });
+                                let buffer_range = audio.get_buffer_size_range();
+                                ui.horizontal(|ui| {
+                                    ui.label("buffer size:");
+                                    ui.group(|ui| {
+                                        if buffer_range.is_none() {
+                                            ui.set_enabled(false);
+                                            *forced_buffer_size = None;
+                                        }
+                                        let mut forced = forced_buffer_size.is_some();
+                                        ui.horizontal(|ui| {
+                                            ui.checkbox(&mut forced, "force");
+                                            ui.set_enabled(forced);
+                                            let mut size = match forced_buffer_size.to_owned() {
+                                                Some(size) => size,
+                                                None => audio.get_buffer_size().unwrap_or(0),
+                                            };
+                                            let range = match buffer_range {
+                                                // limit max to something sensible
+                                                Some((min, max)) => min..=max.min(16384),
+                                                None => 0..=1,
+                                            };
+                                            ui.add(egui::Slider::new(&mut size, range));
+                                            if forced {
+                                                *forced_buffer_size = Some(size);
+                                            } else {
+                                                *forced_buffer_size = None;
+                                            }
+                                            audio.set_forced_buffer_size(*forced_buffer_size);
+                                        });
+                                    });
+                                });
 
-                        audio.pop_each_left_vis_buffer(|value| {
-                            left_vis_buffer.push_back(value);
-                        });
+                                audio.pop_each_left_vis_buffer(|value| {
+                                    left_vis_buffer.push_back(value);
+                                });
 
-                        let mut prev = None;
-                        let mut it = left_vis_buffer.iter().copied(); //.rev();
-                        it.nth(VIS_SIZE / 2 - 1);
-                        for value in &mut it {
-                            if let Some(prev) = prev {
-                                if prev >= 0. && value < 0. {
-                                    break;
+                                let mut prev = None;
+                                let mut it = left_vis_buffer.iter().copied(); //.rev();
+                                it.nth(VIS_SIZE / 2 - 1);
+                                for value in &mut it {
+                                    if let Some(prev) = prev {
+                                        if prev >= 0. && value < 0. {
+                                            break;
+                                        }
+                                    }
+                                    prev = Some(value);
+                                }
+                                let plot_width = ui.available_width().min(300.);
+                                let (_, rect) =
+                                    ui.allocate_space(vec2(plot_width, plot_width * 0.5));
+                                let p = ui.painter_at(rect);
+                                p.rect_filled(rect, 10f32, Color32::BLACK);
+                                let to_rect = emath::RectTransform::from_to(
+                                    Rect::from_x_y_ranges(0.0..=(VIS_SIZE / 2) as f32, -1.0..=1.0),
+                                    rect,
+                                );
+                                p.add(epaint::Shape::line(
+                                    it.take(VIS_SIZE / 2)
+                                        .enumerate()
+                                        .map(|(x, y)| to_rect * pos2(x as f32, y))
+                                        .collect(),
+                                    Stroke::new(1f32, Color32::GRAY),
+                                ));
+                                if left_vis_buffer.len() > VIS_SIZE {
+                                    drop(
+                                        left_vis_buffer.drain(0..left_vis_buffer.len() - VIS_SIZE),
+                                    );
                                 }
+                            });
+                            ui.group(|ui| {
+                                presets.show(ui, params);
+                            });
+                            ui.group(|ui| {
+                                compare_gui(ui, compare, params, synth_sender);
+                                randomizer_gui(ui, randomizer, params, synth_sender, history);
+                                params_gui(ui, params, synth_sender, history, randomizer);
+                            });
+                            // no filesystem access on the web
+                            if cfg!(not(target_arch = "wasm32")) {
+                                ui.group(|ui| {
+                                    tuning_gui(ui, params, scale_path, keyboard_mapping_path);
+                                });
+                            }
+                            if cfg!(debug_assertions) {
+                                ui.group(|ui| {
+                                    dbg_gui(ui);
+                                });
                             }
-                            prev = Some(value);
-                        }
-                        let plot_width = ui.available_width().min(300.);
-                        let (_, rect) = ui.allocate_space(vec2(plot_width, plot_width * 0.5));
-                        let p = ui.painter_at(rect);
-                        p.rect_filled(rect, 10f32, Color32::BLACK);
-                        let to_rect = emath::RectTransform::from_to(
-                            Rect::from_x_y_ranges(0.0..=(VIS_SIZE / 2) as f32, -1.0..=1.0),
-                            rect,
-                        );
-                        p.add(epaint::Shape::line(
-                            it.take(VIS_SIZE / 2)
-                                .enumerate()
-                                .map(|(x, y)| to_rect * pos2(x as f32, y))
-                                .collect(),
-                            Stroke::new(1f32, Color32::GRAY),
-                        ));
-                        if left_vis_buffer.len() > VIS_SIZE {
-                            drop(left_vis_buffer.drain(0..left_vis_buffer.len() - VIS_SIZE));
-                        }
-                    });
-                    ui.group(|ui| {
-                        params_gui(ui, params);
-                    });
-                    if cfg!(debug_assertions) {
-                        ui.group(|ui| {
-                            dbg_gui(ui);
                         });
-                    }
                     // put onscreen keyboard at bottom of window
                     let height = ui.available_size().y;
-                    ui.add_space(height - 20f32);
+                    ui.add_space((height - 20f32).max(0.));
                     keyboard.show(ui);
                 }
             }
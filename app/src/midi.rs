@@ -1,28 +1,28 @@
 use crate::timer::Timer;
 use anyhow::{anyhow, bail, Result};
 use chrono::Duration;
-use crossbeam::channel;
 use log::{error, warn};
 use midir::{MidiInput, MidiInputConnection};
+use pistolhot_synth::MessageSender;
 use std::{
     convert::TryFrom,
     sync::{Arc, Mutex},
 };
-use wmidi::MidiMessage;
-
-type MidiSender = channel::Sender<MidiMessage<'static>>;
 
 pub struct MidiReader {
-    midi_events: MidiSender,
+    midi_events: MessageSender,
     timer: Timer,
     port: Mutex<Option<(MidiInputConnection<()>, String)>>,
+    /// port to connect to when it is available, otherwise the first one is used
+    preferred_port: Mutex<Option<String>>,
 }
 
 impl MidiReader {
-    pub fn new(midi_events: MidiSender) -> Arc<Self> {
+    pub fn new(midi_events: MessageSender) -> Arc<Self> {
         let aself = Arc::new(Self {
             timer: Timer::new(),
             port: Mutex::new(None),
+            preferred_port: Mutex::new(None),
             midi_events,
         });
         aself.init();
@@ -34,7 +34,12 @@ impl MidiReader {
         let r = (|| -> Result<()> {
             let midi = MidiInput::new("pistolhot")?;
             let ports = midi.ports();
-            if let Some(port) = ports.first() {
+            let preferred = self.preferred_port.lock().unwrap().clone();
+            let port = ports
+                .iter()
+                .find(|port| preferred.is_some() && midi.port_name(port).ok() == preferred)
+                .or_else(|| ports.first());
+            if let Some(port) = port {
                 let name = midi.port_name(port)?;
                 let midi_events = self.midi_events.clone();
                 let connection = midi
@@ -43,7 +48,7 @@ impl MidiReader {
                         &name,
                         move |_time_ms, message, _| match wmidi::MidiMessage::try_from(message) {
                             Ok(message) => {
-                                if let Err(e) = midi_events.try_send(message.to_owned()) {
+                                if let Err(e) = midi_events.try_send(message.to_owned().into()) {
                                     error!("error sending midi event {}", e);
                                 }
                             }
@@ -66,18 +71,47 @@ impl MidiReader {
             self.timer
                 .schedule_with_delay(&Duration::seconds(1), move || {
                     if let Some(s) = weak_self.upgrade() {
-                        s.init();
+                        // might have been connected by set_port in the meantime
+                        if s.port.lock().unwrap().is_none() {
+                            s.init();
+                        }
                     }
                 });
         }
     }
 
-    pub fn get_name(&self) -> String {
+    /// connect to a port by name. falls back to the first port if it doesn't exist
+    pub fn set_port(self: &Arc<Self>, name: String) {
+        *self.preferred_port.lock().unwrap() = Some(name);
+        // drop the old connection before making a new one
+        self.port.lock().unwrap().take();
+        self.init();
+    }
+
+    pub fn get_port_names(&self) -> Vec<String> {
+        match MidiInput::new("pistolhot port list") {
+            Ok(midi) => midi
+                .ports()
+                .iter()
+                .filter_map(|port| midi.port_name(port).ok())
+                .collect(),
+            Err(e) => {
+                warn!("error listing midi ports: {}", e);
+                vec![]
+            }
+        }
+    }
+
+    /// name of the connected port
+    pub fn get_port(&self) -> Option<String> {
         self.port
             .lock()
             .unwrap()
             .as_ref()
             .map(|(_, name)| name.clone())
-            .unwrap_or_else(|| "-".to_string())
+    }
+
+    pub fn get_name(&self) -> String {
+        self.get_port().unwrap_or_else(|| "-".to_string())
     }
 }
@@ -0,0 +1,437 @@
+use eframe::egui;
+use log::warn;
+use pistolhot_synth::{factory_presets, Params, Patch, State};
+use std::path::{Path, PathBuf};
+
+enum Source {
+    Factory,
+    /// a file in the user preset folder
+    User(PathBuf),
+}
+
+struct Preset {
+    patch: Patch,
+    source: Source,
+}
+
+impl Preset {
+    fn is_user(&self) -> bool {
+        matches!(self.source, Source::User(_))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn user_folder() -> Option<PathBuf> {
+    Some(
+        dirs::data_dir()?
+            .join("org.deepness.pistolhot")
+            .join("presets"),
+    )
+}
+
+// no filesystem access on the web
+#[cfg(target_arch = "wasm32")]
+fn user_folder() -> Option<PathBuf> {
+    None
+}
+
+/// files that can't be read are left out, and reported in `errors`
+fn read_user_presets(folder: &Path, errors: &mut Vec<String>) -> anyhow::Result<Vec<Preset>> {
+    let mut presets = vec![];
+    if !folder.exists() {
+        return Ok(presets);
+    }
+    for entry in std::fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.extension() != Some("toml".as_ref()) {
+            continue;
+        }
+        match std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| Ok(Patch::from_toml(&source)?))
+        {
+            Ok(patch) => presets.push(Preset {
+                patch,
+                source: Source::User(path),
+            }),
+            Err(e) => errors.push(format!("error reading preset {}: {e}", path.display())),
+        }
+    }
+    presets.sort_by(|a, b| a.patch.name.cmp(&b.patch.name));
+    Ok(presets)
+}
+
+/// keep file names to characters that work on every os
+fn file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.toml", name.trim())
+}
+
+/// factory bank and user preset folder
+pub struct PresetBrowser {
+    folder: Option<PathBuf>,
+    presets: Vec<Preset>,
+    current: Option<usize>,
+    /// the sound as it was when the current preset was loaded or saved, to tell if it is modified
+    loaded_state: Option<State>,
+    tag_filter: Option<String>,
+    /// name for save as and rename
+    name: String,
+    /// comma separated tags for saving
+    tags: String,
+    /// shown until dismissed
+    errors: Vec<String>,
+    /// asking whether to really delete the current preset
+    confirm_delete: bool,
+}
+
+impl Default for PresetBrowser {
+    fn default() -> Self {
+        let mut browser = Self {
+            folder: user_folder(),
+            presets: vec![],
+            current: None,
+            loaded_state: None,
+            tag_filter: None,
+            name: String::new(),
+            tags: String::new(),
+            errors: vec![],
+            confirm_delete: false,
+        };
+        browser.refresh();
+        browser
+    }
+}
+
+impl PresetBrowser {
+    /// rescan the user folder. keeps the current preset selected if it still exists
+    fn refresh(&mut self) {
+        let current_path = match self.current_preset().map(|p| &p.source) {
+            Some(Source::User(path)) => Some(path.clone()),
+            _ => None,
+        };
+        self.presets = factory_presets()
+            .iter()
+            .map(|patch| Preset {
+                patch: patch.clone(),
+                source: Source::Factory,
+            })
+            .collect();
+        let mut errors = vec![];
+        if let Some(folder) = &self.folder {
+            match read_user_presets(folder, &mut errors) {
+                Ok(presets) => self.presets.extend(presets),
+                Err(e) => errors.push(format!("error reading presets: {e}")),
+            }
+        }
+        for error in errors {
+            self.report(error);
+        }
+        // the factory presets come first and never change, so only user ones can move
+        if let Some(path) = current_path {
+            self.current = self.find_user(&path);
+        }
+    }
+
+    fn find_user(&self, path: &Path) -> Option<usize> {
+        self.presets
+            .iter()
+            .position(|preset| matches!(&preset.source, Source::User(p) if p == path))
+    }
+
+    fn report(&mut self, error: String) {
+        warn!("{error}");
+        self.errors.push(error);
+    }
+
+    fn current_preset(&self) -> Option<&Preset> {
+        self.presets.get(self.current?)
+    }
+
+    fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .presets
+            .iter()
+            .flat_map(|preset| preset.patch.tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// indices of the presets matching the tag filter
+    fn filtered(&self) -> Vec<usize> {
+        (0..self.presets.len())
+            .filter(|&i| match &self.tag_filter {
+                Some(tag) => self.presets[i].patch.tags.contains(tag),
+                None => true,
+            })
+            .collect()
+    }
+
+    fn load(&mut self, index: usize, params: &Params) {
+        let patch = &self.presets[index].patch;
+        match patch.to_state() {
+            Ok(state) => {
+                state.apply(params);
+                self.loaded_state = Some(state);
+                self.current = Some(index);
+                self.confirm_delete = false;
+                self.name = patch.name.clone();
+                self.tags = patch.tags.join(", ");
+            }
+            Err(e) => {
+                let error = format!("error loading preset {}: {e}", patch.name);
+                self.report(error);
+            }
+        }
+    }
+
+    /// step through the filtered presets, wrapping around
+    fn step(&mut self, delta: isize, params: &Params) {
+        let filtered = self.filtered();
+        if filtered.is_empty() {
+            return;
+        }
+        let position = self
+            .current
+            .and_then(|current| filtered.iter().position(|&i| i == current));
+        let next = match position {
+            Some(position) => {
+                (position as isize + delta).rem_euclid(filtered.len() as isize) as usize
+            }
+            None => 0,
+        };
+        self.load(filtered[next], params);
+    }
+
+    fn parsed_tags(&self) -> Vec<String> {
+        self.tags
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    fn write(&mut self, path: PathBuf, params: &Params) -> anyhow::Result<()> {
+        // factory presets are by us, not by whoever saves a copy of one
+        let author = self
+            .current_preset()
+            .filter(|preset| preset.is_user())
+            .map(|preset| preset.patch.author.clone())
+            .unwrap_or_default();
+        let patch = Patch {
+            name: self.name.trim().to_string(),
+            author,
+            tags: self.parsed_tags(),
+            ..params.to_patch()
+        };
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+        std::fs::write(&path, patch.to_toml())?;
+        self.loaded_state = Some(State::capture(params));
+        self.refresh();
+        self.current = self.find_user(&path);
+        Ok(())
+    }
+
+    fn save(&mut self, params: &Params) -> anyhow::Result<()> {
+        match self.current_preset().map(|p| &p.source) {
+            Some(Source::User(path)) => {
+                let path = path.clone();
+                self.write(path, params)
+            }
+            _ => anyhow::bail!("only user presets can be saved, use save as"),
+        }
+    }
+
+    fn save_as(&mut self, params: &Params) -> anyhow::Result<()> {
+        let folder = self
+            .folder
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("no preset folder"))?;
+        if self.name.trim().is_empty() {
+            anyhow::bail!("empty preset name");
+        }
+        let path = folder.join(file_name(&self.name));
+        if path.exists() {
+            anyhow::bail!("{} already exists", path.display());
+        }
+        self.write(path, params)
+    }
+
+    fn rename(&mut self) -> anyhow::Result<()> {
+        let (old_path, patch) = match self.current_preset() {
+            Some(Preset {
+                source: Source::User(path),
+                patch,
+            }) => (path.clone(), patch.clone()),
+            _ => anyhow::bail!("only user presets can be renamed"),
+        };
+        if self.name.trim().is_empty() {
+            anyhow::bail!("empty preset name");
+        }
+        let path = old_path.with_file_name(file_name(&self.name));
+        if path != old_path && path.exists() {
+            anyhow::bail!("{} already exists", path.display());
+        }
+        let patch = Patch {
+            name: self.name.trim().to_string(),
+            ..patch
+        };
+        std::fs::write(&path, patch.to_toml())?;
+        if path != old_path {
+            std::fs::remove_file(&old_path)?;
+        }
+        self.refresh();
+        self.current = self.find_user(&path);
+        Ok(())
+    }
+
+    fn delete(&mut self) -> anyhow::Result<()> {
+        if let Some(Source::User(path)) = self.current_preset().map(|p| &p.source) {
+            std::fs::remove_file(path)?;
+            self.current = None;
+            self.loaded_state = None;
+            self.refresh();
+        }
+        Ok(())
+    }
+
+    /// nothing to be modified from if no preset has been loaded
+    fn is_modified(&self, params: &Params) -> bool {
+        self.loaded_state
+            .as_ref()
+            .is_some_and(|state| *state != State::capture(params))
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, params: &Params) {
+        ui.horizontal(|ui| {
+            ui.label("preset:");
+            let name = self
+                .current_preset()
+                .map_or("-", |preset| preset.patch.name.as_str());
+            ui.label(if self.is_modified(params) {
+                format!("{name} (modified)")
+            } else {
+                name.to_string()
+            });
+        });
+        ui.horizontal(|ui| {
+            if ui.button("<").clicked() {
+                self.step(-1, params);
+            }
+            if ui.button(">").clicked() {
+                self.step(1, params);
+            }
+            ui.label("tag:");
+            let mut tag_filter = self.tag_filter.clone();
+            egui::ComboBox::from_id_source("preset tag filter")
+                .selected_text(tag_filter.as_deref().unwrap_or("all"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut tag_filter, None, "all");
+                    for tag in self.all_tags() {
+                        ui.selectable_value(&mut tag_filter, Some(tag.clone()), tag);
+                    }
+                });
+            self.tag_filter = tag_filter;
+            if self.folder.is_some() && ui.button("refresh").clicked() {
+                self.refresh();
+            }
+        });
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .max_height(100.)
+            .show(ui, |ui| {
+                for i in self.filtered() {
+                    let preset = &self.presets[i];
+                    let label = if preset.is_user() {
+                        preset.patch.name.clone()
+                    } else {
+                        format!("{} (factory)", preset.patch.name)
+                    };
+                    if ui
+                        .selectable_label(self.current == Some(i), label)
+                        .clicked()
+                    {
+                        clicked = Some(i);
+                    }
+                }
+            });
+        if let Some(i) = clicked {
+            self.load(i, params);
+        }
+        if self.folder.is_some() {
+            ui.horizontal(|ui| {
+                ui.label("name:");
+                ui.text_edit_singleline(&mut self.name);
+            });
+            ui.horizontal(|ui| {
+                ui.label("tags:");
+                ui.text_edit_singleline(&mut self.tags);
+            });
+            let is_user = self.current_preset().is_some_and(Preset::is_user);
+            ui.horizontal(|ui| {
+                let save = ui.add_enabled(is_user, egui::Button::new("save")).clicked();
+                let save_as = ui.button("save as").clicked();
+                let rename = ui
+                    .add_enabled(is_user, egui::Button::new("rename"))
+                    .clicked();
+                let delete = ui
+                    .add_enabled(is_user, egui::Button::new("delete"))
+                    .clicked();
+                let result = if save {
+                    self.save(params)
+                } else if save_as {
+                    self.save_as(params)
+                } else if rename {
+                    self.rename()
+                } else {
+                    Ok(())
+                };
+                if let Err(e) = result {
+                    self.report(e.to_string());
+                }
+                if delete {
+                    self.confirm_delete = true;
+                }
+            });
+            // there is no undoing it
+            if self.confirm_delete && is_user {
+                ui.horizontal(|ui| {
+                    let name = self
+                        .current_preset()
+                        .map_or("", |preset| preset.patch.name.as_str());
+                    ui.label(format!("delete {name}?"));
+                    if ui.button("yes").clicked() {
+                        self.confirm_delete = false;
+                        if let Err(e) = self.delete() {
+                            self.report(e.to_string());
+                        }
+                    }
+                    if ui.button("no").clicked() {
+                        self.confirm_delete = false;
+                    }
+                });
+            }
+        }
+        if !self.errors.is_empty() {
+            for error in &self.errors {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            if ui.button("dismiss").clicked() {
+                self.errors.clear();
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use eframe::epi;
+
+const AUDIO_DEVICE_KEY: &str = "audio_device";
+const FORCED_BUFFER_SIZE_KEY: &str = "forced_buffer_size";
+const MIDI_PORT_KEY: &str = "midi_port";
+const PATCH_KEY: &str = "patch";
+
+/// what is remembered between runs
+#[derive(Default)]
+pub struct Settings {
+    pub audio_device: Option<String>,
+    pub forced_buffer_size: Option<u32>,
+    pub midi_port: Option<String>,
+    /// the current sound as a toml patch
+    pub patch: Option<String>,
+}
+
+// there is no way to remove a key, so unset values are stored as empty strings
+
+fn get(storage: &dyn epi::Storage, key: &str) -> Option<String> {
+    storage.get_string(key).filter(|value| !value.is_empty())
+}
+
+fn set(storage: &mut dyn epi::Storage, key: &str, value: Option<String>) {
+    storage.set_string(key, value.unwrap_or_default());
+}
+
+impl Settings {
+    pub fn load(storage: &dyn epi::Storage) -> Self {
+        Self {
+            audio_device: get(storage, AUDIO_DEVICE_KEY),
+            forced_buffer_size: get(storage, FORCED_BUFFER_SIZE_KEY)
+                .and_then(|size| size.parse().ok()),
+            midi_port: get(storage, MIDI_PORT_KEY),
+            patch: get(storage, PATCH_KEY),
+        }
+    }
+
+    pub fn save(&self, storage: &mut dyn epi::Storage) {
+        set(storage, AUDIO_DEVICE_KEY, self.audio_device.clone());
+        set(
+            storage,
+            FORCED_BUFFER_SIZE_KEY,
+            self.forced_buffer_size.map(|size| size.to_string()),
+        );
+        set(storage, MIDI_PORT_KEY, self.midi_port.clone());
+        set(storage, PATCH_KEY, self.patch.clone());
+    }
+}
@@ -0,0 +1,108 @@
+// a second double pendulum that isn't heard, swinging slowly at the chaos rate. its motion
+// never repeats, which makes it a modulation source for organic movement
+
+use glam::vec4;
+
+use crate::{pendulum::Pendulum, simulator::Simulator, Tap};
+
+/// moves slowly enough that it doesn't need to be advanced every sample
+const UPDATE_INTERVAL: usize = 16;
+
+/// how quickly the energy is corrected for integration drift
+const ENERGY_ADJUSTMENT: f32 = 0.001;
+
+#[derive(Clone)]
+pub(crate) struct ChaosPendulum {
+    simulator: Simulator,
+    /// samples until the next update
+    countdown: usize,
+}
+
+impl Default for ChaosPendulum {
+    fn default() -> Self {
+        Self {
+            simulator: Simulator {
+                pendulum: Pendulum {
+                    // start out of balance, so it's moving from the start
+                    t_pt: vec4(2., -1., 0., 0.),
+                    ..Pendulum::default()
+                },
+                step_size: 1. / 1000.,
+                ..Simulator::default()
+            },
+            countdown: 0,
+        }
+    }
+}
+
+impl ChaosPendulum {
+    /// the swing frequency of the pendulum at small angles, in Hz
+    fn natural_frequency(&self) -> f32 {
+        let Pendulum { g, length, .. } = self.simulator.pendulum;
+        (g / (length.x + length.y)).sqrt() / std::f32::consts::TAU
+    }
+
+    /// advance one sample. `rate` is in Hz. `amount` in 0..=1 is the energy, from barely swinging
+    /// to enough to flip both arms over
+    pub fn run(&mut self, rate: f32, amount: f32, sample_rate: u32) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return;
+        }
+        self.countdown = UPDATE_INTERVAL - 1;
+        let Pendulum {
+            g, mass, length, ..
+        } = self.simulator.pendulum;
+        // the potential energy with both arms pointing up
+        let max_potential = 2. * g * ((mass.x + mass.y) * length.x + mass.y * length.y);
+        let energy = amount.clamp(0., 1.) * max_potential;
+        // run the simulation faster instead of changing the pendulum, so the energy stays put
+        let elapsed = UPDATE_INTERVAL as f32 / sample_rate as f32 * rate / self.natural_frequency();
+        self.simulator.update(elapsed, energy, ENERGY_ADJUSTMENT);
+    }
+
+    /// horizontal position of the tip, -1..=1
+    pub fn x(&self) -> f32 {
+        self.simulator.get_tap(Tap::TipX)
+    }
+
+    /// height of the tip, centered to -1..=1 since it's a modulation source rather than audio
+    pub fn y(&self) -> f32 {
+        self.simulator.get_tap(Tap::TipY) - 1.
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChaosPendulum;
+
+    #[test]
+    fn bounded_and_not_repeating() {
+        let mut chaos = ChaosPendulum::default();
+        let sample_rate = 1000;
+        let values: Vec<(f32, f32)> = (0..60 * sample_rate)
+            .map(|_| {
+                chaos.run(2., 1., sample_rate as u32);
+                (chaos.x(), chaos.y())
+            })
+            .collect();
+        assert!(values
+            .iter()
+            .all(|(x, y)| (-1. ..=1.).contains(x) && (-1. ..=1.).contains(y)));
+        let (min, max) = values.iter().fold((1f32, -1f32), |(min, max), &(x, _)| {
+            (min.min(x), max.max(x))
+        });
+        assert!(max - min > 0.5, "{min} {max}");
+        // unlike an lfo, no stretch of motion comes back as it was
+        let window = sample_rate;
+        let start = &values[..window];
+        for offset in (window..values.len() - window).step_by(10) {
+            let distance: f32 = start
+                .iter()
+                .zip(&values[offset..offset + window])
+                .map(|((x0, y0), (x1, y1))| (x1 - x0).abs() + (y1 - y0).abs())
+                .sum();
+            assert!(distance / window as f32 > 0.01, "repeats at {offset}");
+        }
+    }
+}
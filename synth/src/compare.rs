@@ -0,0 +1,107 @@
+// snapshot slots for comparing settings. the active slot is whatever is currently playing,
+// the others hold the sound as it was when switching away from them
+
+use egui::Ui;
+
+use crate::{MessageSender, Params, State};
+
+pub const NUM_SLOTS: usize = 2;
+
+const SLOT_NAMES: [&str; NUM_SLOTS] = ["A", "B"];
+
+#[derive(Default)]
+pub struct CompareSlots {
+    /// empty until the slot has been switched away from or copied to
+    slots: [Option<State>; NUM_SLOTS],
+    active: usize,
+}
+
+impl CompareSlots {
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// switching to an empty slot keeps the current sound
+    pub fn select(&mut self, slot: usize, params: &Params, sender: &MessageSender) {
+        if slot == self.active || slot >= NUM_SLOTS {
+            return;
+        }
+        self.slots[self.active] = Some(State::capture(params));
+        if let Some(state) = &self.slots[slot] {
+            state.send(params, sender);
+        }
+        self.active = slot;
+    }
+
+    pub fn copy(&mut self, from: usize, to: usize, params: &Params, sender: &MessageSender) {
+        if from == to || from >= NUM_SLOTS || to >= NUM_SLOTS {
+            return;
+        }
+        let state = if from == self.active {
+            State::capture(params)
+        } else {
+            match &self.slots[from] {
+                Some(state) => state.clone(),
+                None => return,
+            }
+        };
+        if to == self.active {
+            state.send(params, sender);
+        }
+        self.slots[to] = Some(state);
+    }
+}
+
+pub fn compare_gui(ui: &mut Ui, slots: &mut CompareSlots, params: &Params, sender: &MessageSender) {
+    ui.horizontal(|ui| {
+        ui.label("compare:");
+        let mut selected = slots.active();
+        for (i, name) in SLOT_NAMES.iter().enumerate() {
+            ui.selectable_value(&mut selected, i, *name);
+        }
+        slots.select(selected, params, sender);
+        if ui.button("copy A to B").clicked() {
+            slots.copy(0, 1, params, sender);
+        }
+        if ui.button("copy B to A").clicked() {
+            slots.copy(1, 0, params, sender);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::CompareSlots;
+    use crate::{Message, MessageChannel, Param, Params};
+    use crossbeam::channel;
+
+    /// what the synth does with the messages
+    fn receive(rx: &MessageChannel, params: &Params) {
+        for message in rx.try_iter() {
+            if let Message::SetParam(param, value) = message {
+                params.set(param, value);
+            }
+        }
+    }
+
+    #[test]
+    fn switch_and_copy() {
+        let (tx, rx) = channel::unbounded();
+        let params = Params::default();
+        let mut slots = CompareSlots::default();
+        params.set(Param::Drive, 10.);
+        // b starts out as a copy of a
+        slots.select(1, &params, &tx);
+        assert!(rx.try_recv().is_err());
+        params.set(Param::Drive, 20.);
+        slots.select(0, &params, &tx);
+        receive(&rx, &params);
+        assert_eq!(params.get(Param::Drive), 10.);
+        slots.select(1, &params, &tx);
+        receive(&rx, &params);
+        assert_eq!(params.get(Param::Drive), 20.);
+        slots.copy(0, 1, &params, &tx);
+        receive(&rx, &params);
+        assert_eq!(params.get(Param::Drive), 10.);
+    }
+}
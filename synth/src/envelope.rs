@@ -0,0 +1,68 @@
+/// times in seconds, sustain as a level
+#[derive(Clone, Copy, Debug)]
+pub struct Adsr {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Stage {
+    Attack,
+    // also covers the sustain stage
+    Decay,
+    Release,
+}
+
+/// linear adsr envelope generator
+#[derive(Clone)]
+pub struct Envelope {
+    stage: Stage,
+    level: f32,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self {
+            stage: Stage::Release,
+            level: 0.,
+        }
+    }
+}
+
+impl Envelope {
+    /// start the attack from the current level
+    pub fn trigger(&mut self) {
+        self.stage = Stage::Attack;
+    }
+
+    pub fn release(&mut self) {
+        self.stage = Stage::Release;
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn update(&mut self, elapsed: f32, adsr: &Adsr) -> f32 {
+        match self.stage {
+            Stage::Attack => {
+                self.level += elapsed / adsr.attack.max(f32::EPSILON);
+                if self.level >= 1. {
+                    self.level = 1.;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                // fall at a rate that takes `decay` seconds to go from full level to sustain
+                let rate = (1. - adsr.sustain) / adsr.decay.max(f32::EPSILON);
+                self.level = (self.level - elapsed * rate).max(adsr.sustain);
+            }
+            Stage::Release => {
+                self.level = (self.level - elapsed / adsr.release.max(f32::EPSILON)).max(0.);
+            }
+        }
+        self.level
+    }
+}
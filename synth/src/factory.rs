@@ -0,0 +1,65 @@
+// patches shipped with the synth
+
+use once_cell::sync::Lazy;
+
+use crate::Patch;
+
+const SOURCES: [&str; 9] = [
+    include_str!("../presets/init.toml"),
+    include_str!("../presets/glass_bell.toml"),
+    include_str!("../presets/tubular_bell.toml"),
+    include_str!("../presets/slow_drone.toml"),
+    include_str!("../presets/chaos_drone.toml"),
+    include_str!("../presets/chaotic_lead.toml"),
+    include_str!("../presets/folded_lead.toml"),
+    include_str!("../presets/pluck.toml"),
+    include_str!("../presets/muted_pluck.toml"),
+];
+
+static FACTORY_PRESETS: Lazy<Vec<Patch>> = Lazy::new(|| {
+    SOURCES
+        .iter()
+        .map(|source| Patch::from_toml(source).expect("invalid factory preset"))
+        .collect()
+});
+
+/// the first one is the default sound
+pub fn factory_presets() -> &'static [Patch] {
+    &FACTORY_PRESETS
+}
+
+#[cfg(test)]
+mod test {
+    use super::factory_presets;
+    use crate::{Synth, SynthPlayer};
+    use crossbeam::channel;
+
+    #[test]
+    fn factory_presets_render() {
+        for patch in factory_presets() {
+            let (tx, rx) = channel::unbounded();
+            let mut synth = Synth::new(rx);
+            synth.get_params().from_patch(patch).unwrap();
+            tx.send(
+                wmidi::MidiMessage::NoteOn(
+                    wmidi::Channel::Ch1,
+                    wmidi::Note::C4,
+                    wmidi::U7::from_u8_lossy(100),
+                )
+                .into(),
+            )
+            .unwrap();
+            let mut data = vec![0f32; 2 * 24000];
+            synth.play(48000, 2, &mut data);
+            assert!(
+                data.iter().all(|sample| sample.is_finite()),
+                "{} produced non finite samples",
+                patch.name
+            );
+            let peak = data
+                .iter()
+                .fold(0f32, |peak, sample| peak.max(sample.abs()));
+            assert!(peak > 0.01, "{} is silent", patch.name);
+        }
+    }
+}
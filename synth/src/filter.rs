@@ -0,0 +1,152 @@
+use crate::envelope::{Adsr, Envelope};
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FilterMode {
+    LowPass,
+    BandPass,
+    HighPass,
+    Notch,
+}
+
+impl FilterMode {
+    pub const ALL: [FilterMode; 4] = [
+        FilterMode::LowPass,
+        FilterMode::BandPass,
+        FilterMode::HighPass,
+        FilterMode::Notch,
+    ];
+}
+
+/// frequency key tracking is relative to. middle c
+pub const KEY_TRACKING_CENTER_FREQ: f32 = 261.6256;
+const MIN_CUTOFF: f32 = 20.;
+/// time constant of the cutoff smoothing in seconds
+const CUTOFF_SMOOTHING: f32 = 0.005;
+
+struct Coefficients {
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    k: f32,
+}
+
+impl Coefficients {
+    fn new(cutoff: f32, resonance: f32, sample_rate: f32) -> Self {
+        let g = (PI * cutoff / sample_rate).tan();
+        // resonance 0 gives a q of 0.5, approaching 1 makes it self oscillate
+        let k = 2. * (1. - resonance.clamp(0., 0.99));
+        let a1 = 1. / (1. + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        Self { a1, a2, a3, k }
+    }
+}
+
+/// cutoff of the dc blocker in Hz
+const DC_BLOCKER_FREQ: f32 = 10.;
+
+/// one pole one zero highpass to remove dc offset
+#[derive(Clone, Default)]
+pub struct DcBlocker {
+    x1: f32,
+    y1: f32,
+}
+
+impl DcBlocker {
+    pub fn run(&mut self, input: f32, sample_rate: u32) -> f32 {
+        let r = 1. - 2. * PI * DC_BLOCKER_FREQ / sample_rate as f32;
+        let y = input - self.x1 + r * self.y1;
+        self.x1 = input;
+        self.y1 = y;
+        y
+    }
+}
+
+/// trapezoidal integrated state variable filter, as described by Andrew Simper
+#[derive(Clone, Default)]
+struct StateVariableFilter {
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl StateVariableFilter {
+    fn run(&mut self, input: f32, c: &Coefficients, mode: FilterMode) -> f32 {
+        let v3 = input - self.ic2eq;
+        let v1 = c.a1 * self.ic1eq + c.a2 * v3;
+        let v2 = self.ic2eq + c.a2 * self.ic1eq + c.a3 * v3;
+        self.ic1eq = 2. * v1 - self.ic1eq;
+        self.ic2eq = 2. * v2 - self.ic2eq;
+        match mode {
+            FilterMode::LowPass => v2,
+            FilterMode::BandPass => v1,
+            FilterMode::HighPass => input - c.k * v1 - v2,
+            FilterMode::Notch => input - c.k * v1,
+        }
+    }
+}
+
+pub struct FilterSettings {
+    pub mode: FilterMode,
+    /// in Hz
+    pub cutoff: f32,
+    pub resonance: f32,
+    /// cutoff modulation by the envelope in octaves
+    pub envelope_amount: f32,
+    pub envelope: Adsr,
+    /// 1 makes the cutoff follow the note pitch
+    pub key_tracking: f32,
+    /// cutoff modulation by velocity in octaves
+    pub velocity_amount: f32,
+}
+
+/// stereo filter with its own envelope
+#[derive(Clone, Default)]
+pub struct FilterSection {
+    filters: [StateVariableFilter; 2],
+    envelope: Envelope,
+    /// smoothed cutoff in octaves
+    smoothed_cutoff: Option<f32>,
+}
+
+impl FilterSection {
+    pub fn trigger(&mut self) {
+        self.envelope.trigger();
+    }
+
+    pub fn release(&mut self) {
+        self.envelope.release();
+    }
+
+    pub fn envelope_level(&self) -> f32 {
+        self.envelope.level()
+    }
+
+    /// `frequency` and `velocity` are those of the current note
+    pub fn run(
+        &mut self,
+        input: (f32, f32),
+        settings: &FilterSettings,
+        frequency: f32,
+        velocity: f32,
+        sample_rate: u32,
+    ) -> (f32, f32) {
+        let sample_rate = sample_rate as f32;
+        let envelope = self.envelope.update(1. / sample_rate, &settings.envelope);
+        let key_octaves = (frequency / KEY_TRACKING_CENTER_FREQ).log2();
+        let target = settings.cutoff.max(MIN_CUTOFF).log2()
+            + settings.envelope_amount * envelope
+            + settings.key_tracking * key_octaves
+            + settings.velocity_amount * velocity;
+        let smoothing = 1. - (-1. / (CUTOFF_SMOOTHING * sample_rate)).exp();
+        let smoothed = self.smoothed_cutoff.get_or_insert(target);
+        *smoothed += (target - *smoothed) * smoothing;
+        let cutoff = smoothed.exp2().clamp(MIN_CUTOFF, sample_rate * 0.49);
+        let coefficients = Coefficients::new(cutoff, settings.resonance, sample_rate);
+        let [left, right] = &mut self.filters;
+        (
+            left.run(input.0, &coefficients, settings.mode),
+            right.run(input.1, &coefficients, settings.mode),
+        )
+    }
+}
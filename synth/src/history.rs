@@ -0,0 +1,143 @@
+use log::warn;
+
+use crate::{Message, MessageSender, Param};
+
+/// steps older than this are forgotten
+const MAX_STEPS: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Change {
+    param: Param,
+    before: f32,
+    after: f32,
+}
+
+/// one undoable step. may change several parameters
+type Step = Vec<Change>;
+
+/// undo and redo stacks of parameter edits
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    /// changes are collected into this while a drag is ongoing
+    open: Option<Step>,
+}
+
+fn send(sender: &MessageSender, param: Param, value: f32) {
+    if let Err(e) = sender.try_send(Message::SetParam(param, value)) {
+        warn!("error sending param change {}", e);
+    }
+}
+
+impl History {
+    /// start collecting changes into a single step, for continuous edits like slider drags
+    pub fn begin(&mut self) {
+        self.end();
+        self.open = Some(vec![]);
+    }
+
+    pub fn end(&mut self) {
+        if let Some(step) = self.open.take() {
+            self.push(step);
+        }
+    }
+
+    fn push(&mut self, step: Step) {
+        if step.is_empty() {
+            return;
+        }
+        self.undo.push(step);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// remember a change that has been sent to the synth
+    pub fn record(&mut self, param: Param, before: f32, after: f32) {
+        let change = Change {
+            param,
+            before,
+            after,
+        };
+        match &mut self.open {
+            Some(step) => match step.iter_mut().find(|c| c.param == param) {
+                // keep the value from before the drag started
+                Some(existing) => existing.after = after,
+                None => step.push(change),
+            },
+            None => self.push(vec![change]),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.open.as_ref().is_some_and(|s| !s.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo(&mut self, sender: &MessageSender) {
+        self.end();
+        if let Some(step) = self.undo.pop() {
+            for change in step.iter().rev() {
+                send(sender, change.param, change.before);
+            }
+            self.redo.push(step);
+        }
+    }
+
+    pub fn redo(&mut self, sender: &MessageSender) {
+        self.end();
+        if let Some(step) = self.redo.pop() {
+            for change in &step {
+                send(sender, change.param, change.after);
+            }
+            self.undo.push(step);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::History;
+    use crate::{Message, Param};
+    use crossbeam::channel;
+
+    #[test]
+    fn coalesce_drag() {
+        let (tx, rx) = channel::unbounded();
+        let mut history = History::default();
+        history.record(Param::Attack, 0.1, 0.2);
+        history.begin();
+        history.record(Param::Drive, 0., 1.);
+        history.record(Param::Drive, 1., 2.);
+        history.record(Param::Drive, 2., 3.);
+        history.end();
+        history.undo(&tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Message::SetParam(Param::Drive, value)) if value == 0.
+        ));
+        assert!(rx.try_recv().is_err());
+        history.undo(&tx);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Message::SetParam(Param::Attack, value)) if value == 0.1
+        ));
+        assert!(!history.can_undo());
+        history.redo(&tx);
+        history.redo(&tx);
+        let values: Vec<_> = rx.try_iter().collect();
+        assert!(matches!(
+            values[..],
+            [
+                Message::SetParam(Param::Attack, _),
+                Message::SetParam(Param::Drive, drive)
+            ] if drive == 3.
+        ));
+        assert!(!history.can_redo());
+    }
+}
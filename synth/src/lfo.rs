@@ -0,0 +1,284 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::Param;
+
+pub const NUM_LFOS: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    /// a new random value each cycle
+    SampleAndHold,
+    /// glides between random values
+    SmoothRandom,
+}
+
+impl LfoShape {
+    pub const ALL: [LfoShape; 6] = [
+        LfoShape::Sine,
+        LfoShape::Triangle,
+        LfoShape::Saw,
+        LfoShape::Square,
+        LfoShape::SampleAndHold,
+        LfoShape::SmoothRandom,
+    ];
+}
+
+/// cycle length in beats of the host tempo, or free running at the rate parameter
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LfoSync {
+    Free,
+    FourBars,
+    TwoBars,
+    Bar,
+    Half,
+    Quarter,
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+}
+
+impl LfoSync {
+    pub const ALL: [LfoSync; 9] = [
+        LfoSync::Free,
+        LfoSync::FourBars,
+        LfoSync::TwoBars,
+        LfoSync::Bar,
+        LfoSync::Half,
+        LfoSync::Quarter,
+        LfoSync::Eighth,
+        LfoSync::Sixteenth,
+        LfoSync::ThirtySecond,
+    ];
+
+    fn beats(self) -> Option<f32> {
+        match self {
+            LfoSync::Free => None,
+            LfoSync::FourBars => Some(16.),
+            LfoSync::TwoBars => Some(8.),
+            LfoSync::Bar => Some(4.),
+            LfoSync::Half => Some(2.),
+            LfoSync::Quarter => Some(1.),
+            LfoSync::Eighth => Some(0.5),
+            LfoSync::Sixteenth => Some(0.25),
+            LfoSync::ThirtySecond => Some(0.125),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LfoMode {
+    /// keeps running across notes
+    Global,
+    /// restarts with every note
+    PerVoice,
+}
+
+impl LfoMode {
+    pub const ALL: [LfoMode; 2] = [LfoMode::Global, LfoMode::PerVoice];
+}
+
+/// where an lfo goes on its own, at its depth. pitch and energy aren't parameters, so this is
+/// the only way to reach them. the modulation matrix can take the lfos to any parameter as well,
+/// including the depth here, to have another source scale the vibrato for example
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LfoDestination {
+    Chaoticity,
+    /// the energy the envelope drives the pendulum towards
+    Energy,
+    FilterCutoff,
+    Pitch,
+}
+
+impl LfoDestination {
+    pub const ALL: [LfoDestination; 4] = [
+        LfoDestination::Chaoticity,
+        LfoDestination::Energy,
+        LfoDestination::FilterCutoff,
+        LfoDestination::Pitch,
+    ];
+}
+
+pub(crate) struct LfoParams {
+    pub shape: Param,
+    pub rate: Param,
+    pub sync: Param,
+    pub phase: Param,
+    pub mode: Param,
+    pub destination: Param,
+    pub depth: Param,
+}
+
+pub(crate) const LFO_PARAMS: [LfoParams; NUM_LFOS] = [
+    LfoParams {
+        shape: Param::Lfo1Shape,
+        rate: Param::Lfo1Rate,
+        sync: Param::Lfo1Sync,
+        phase: Param::Lfo1Phase,
+        mode: Param::Lfo1Mode,
+        destination: Param::Lfo1Destination,
+        depth: Param::Lfo1Depth,
+    },
+    LfoParams {
+        shape: Param::Lfo2Shape,
+        rate: Param::Lfo2Rate,
+        sync: Param::Lfo2Sync,
+        phase: Param::Lfo2Phase,
+        mode: Param::Lfo2Mode,
+        destination: Param::Lfo2Destination,
+        depth: Param::Lfo2Depth,
+    },
+];
+
+pub struct LfoSettings {
+    pub shape: LfoShape,
+    /// in Hz, when not synced
+    pub rate: f32,
+    pub sync: LfoSync,
+    /// in degrees
+    pub phase: f32,
+    pub mode: LfoMode,
+    pub destination: LfoDestination,
+    /// -1..=1
+    pub depth: f32,
+}
+
+// modulation at full depth
+const CHAOTICITY_DEPTH: f32 = 0.5;
+/// relative to the envelope's energy
+const ENERGY_DEPTH: f32 = 1.;
+const CUTOFF_DEPTH_OCTAVES: f32 = 4.;
+const PITCH_DEPTH_SEMITONES: f32 = 2.;
+
+/// the summed lfo output for each destination
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Modulation {
+    /// added to the chaoticity
+    pub chaoticity: f32,
+    /// relative change of the energy
+    pub energy: f32,
+    pub cutoff_octaves: f32,
+    pub pitch_semitones: f32,
+}
+
+impl Modulation {
+    pub fn add(&mut self, destination: LfoDestination, value: f32) {
+        match destination {
+            LfoDestination::Chaoticity => self.chaoticity += value * CHAOTICITY_DEPTH,
+            LfoDestination::Energy => self.energy += value * ENERGY_DEPTH,
+            LfoDestination::FilterCutoff => self.cutoff_octaves += value * CUTOFF_DEPTH_OCTAVES,
+            LfoDestination::Pitch => self.pitch_semitones += value * PITCH_DEPTH_SEMITONES,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Lfo {
+    /// 0..1 through the current cycle, without the phase offset
+    phase: f32,
+    /// random values for the random shapes
+    previous: f32,
+    held: f32,
+    rng: fastrand::Rng,
+}
+
+impl Lfo {
+    pub fn new(seed: u64) -> Self {
+        let rng = fastrand::Rng::with_seed(seed);
+        Self {
+            phase: 0.,
+            previous: rng.f32() * 2. - 1.,
+            held: rng.f32() * 2. - 1.,
+            rng,
+        }
+    }
+
+    /// for notes in per voice mode
+    pub fn trigger(&mut self) {
+        self.phase = 0.;
+    }
+
+    /// output in -1..=1, before the depth. `tempo` is in beats per minute
+    pub fn run(&mut self, settings: &LfoSettings, tempo: f32, sample_rate: u32) -> f32 {
+        let phase = (self.phase + settings.phase / 360.).rem_euclid(1.);
+        let value = match settings.shape {
+            LfoShape::Sine => (phase * TAU).sin(),
+            LfoShape::Triangle => 1. - 4. * ((phase + 0.25).fract() - 0.5).abs(),
+            LfoShape::Saw => 2. * (phase + 0.5).fract() - 1.,
+            LfoShape::Square => {
+                if phase < 0.5 {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+            LfoShape::SampleAndHold => self.held,
+            LfoShape::SmoothRandom => {
+                let t = (1. - (self.phase * PI).cos()) / 2.;
+                self.previous + (self.held - self.previous) * t
+            }
+        };
+        let frequency = match settings.sync.beats() {
+            Some(beats) => tempo / 60. / beats,
+            None => settings.rate,
+        };
+        self.phase += frequency / sample_rate as f32;
+        if self.phase >= 1. {
+            self.phase = self.phase.fract();
+            self.previous = self.held;
+            self.held = self.rng.f32() * 2. - 1.;
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Lfo, LfoDestination, LfoMode, LfoSettings, LfoShape, LfoSync};
+
+    #[test]
+    fn shapes_in_range() {
+        for shape in LfoShape::ALL {
+            let settings = LfoSettings {
+                shape,
+                rate: 100.,
+                sync: LfoSync::Free,
+                phase: 90.,
+                mode: LfoMode::Global,
+                destination: LfoDestination::Pitch,
+                depth: 1.,
+            };
+            let mut lfo = Lfo::new(0);
+            let values: Vec<f32> = (0..4800).map(|_| lfo.run(&settings, 120., 48000)).collect();
+            assert!(
+                values.iter().all(|value| (-1. ..=1.).contains(value)),
+                "{shape:?}"
+            );
+            let (min, max) = values
+                .iter()
+                .fold((1f32, -1f32), |(min, max), &v| (min.min(v), max.max(v)));
+            assert!(max - min > 0.1, "{shape:?} doesn't move");
+        }
+    }
+
+    #[test]
+    fn tempo_sync() {
+        let settings = LfoSettings {
+            shape: LfoShape::Square,
+            rate: 1.,
+            sync: LfoSync::Quarter,
+            phase: 0.,
+            mode: LfoMode::Global,
+            destination: LfoDestination::Pitch,
+            depth: 1.,
+        };
+        let mut lfo = Lfo::new(0);
+        // a quarter note at 120 bpm is half a second, 128 samples at 256 Hz
+        let values: Vec<f32> = (0..128).map(|_| lfo.run(&settings, 120., 256)).collect();
+        assert!(values[..64].iter().all(|&value| value == 1.));
+        assert!(values[64..].iter().all(|&value| value == -1.));
+    }
+}
@@ -5,20 +5,61 @@ TODO change friction to instead be some sort of energy dissipation
 TODO calculate length only using the first part of pendulum?
 */
 
+mod chaos;
+mod compare;
 #[macro_use]
 mod dbg_gui;
+mod envelope;
+mod factory;
+mod filter;
+mod history;
+mod lfo;
+mod loudness;
+mod message;
+mod mod_matrix;
+mod params;
 mod params_gui;
+mod patch;
 mod pendulum;
+mod programs;
+mod randomize;
+mod saturation;
 mod simulator;
-use biquad::{Biquad, ToHertz};
-use crossbeam::{atomic::AtomicCell, channel};
+mod smoothing;
+mod state;
+mod tuning;
+mod velocity;
+use chaos::ChaosPendulum;
+pub use compare::{compare_gui, CompareSlots, NUM_SLOTS};
+use crossbeam::atomic::AtomicCell;
 pub use dbg_gui::dbg_gui;
+pub use factory::factory_presets;
+pub use filter::FilterMode;
+use filter::{DcBlocker, FilterSection};
 use glam::{vec2, Vec2};
+pub use history::History;
+use lfo::{Lfo, Modulation};
+pub use lfo::{LfoDestination, LfoMode, LfoShape, LfoSync, NUM_LFOS};
+use loudness::Normalizer;
+pub use message::{Message, MessageChannel, MessageSender, CHANNEL_CAPACITY};
+use mod_matrix::ModSources;
+pub use mod_matrix::{ModCurve, ModSource, NUM_MOD_SLOTS};
+pub use params::{Curve, Param, ParamDescriptor, Params, Randomize, NUM_PARAMS};
 pub use params_gui::params_gui;
+pub use patch::{Patch, PatchError, PATCH_VERSION};
 use pendulum::Pendulum;
+pub use programs::Programs;
+pub use randomize::{randomizer_gui, Randomizer};
+use saturation::db_to_gain;
+pub use saturation::Saturation;
 use simulator::Simulator;
+pub use simulator::{Tap, NUM_TAPS};
+use smoothing::SmoothedParams;
+pub use state::State;
 use static_assertions::const_assert;
-use std::{f32::consts::PI, ops::RangeInclusive, sync::Arc};
+use std::{collections::VecDeque, f32::consts::PI, sync::Arc};
+pub use tuning::{KeyboardMapping, ParseError, Scale, Tuning};
+pub use velocity::VelocityCurve;
 use wmidi::MidiMessage;
 
 use crate::dbg_gui::dbg_value;
@@ -30,8 +71,6 @@ fn u7_to_f32(value: wmidi::U7) -> f32 {
         / (u8::from(wmidi::U7::MAX) - u8::from(wmidi::U7::MIN)) as f32
 }
 
-pub type MidiChannel = channel::Receiver<MidiMessage<'static>>;
-
 #[derive(Clone)]
 enum NoteState {
     Pressed(u32),
@@ -51,59 +90,32 @@ struct NoteEvent {
     note: wmidi::Note,
     state: NoteState,
     velocity: f32,
+    /// in Hz, according to the tuning when the note was pressed
+    frequency: f32,
 }
 
-pub const CHAOTICITY_RANGE: RangeInclusive<f32> = 0.1f32..=1f32;
-pub const ATTACK_RANGE: RangeInclusive<f32> = 0f32..=1f32;
-pub const DECAY_DELAY_RANGE: RangeInclusive<f32> = 0f32..=10f32;
-pub const DECAY_RANGE: RangeInclusive<f32> = 0f32..=1f32;
-pub const SUSTAIN_RANGE: RangeInclusive<f32> = 0f32..=1f32;
-pub const RELEASE_RANGE: RangeInclusive<f32> = 0f32..=1f32;
-
-// TODO handle params using messages instead?
-pub struct Params {
-    pub chaoticity: AtomicCell<f32>,
-    pub attack: AtomicCell<f32>,
-    pub decay_delay: AtomicCell<f32>,
-    pub decay: AtomicCell<f32>,
-    pub sustain: AtomicCell<f32>,
-    pub release: AtomicCell<f32>,
+/// how the pendulum motion is mapped to the left and right channels
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StereoMode {
+    /// mix of the output taps in both channels
+    Mono,
+    /// first arm's bob left, tip right
+    ArmTip,
+    /// horizontal tip displacement left, vertical right
+    HorizontalVertical,
+    /// sum of the arm angles as mid, difference as side
+    MidSide,
 }
 
-impl Params {
-    fn get_attack(&self) -> f32 {
-        self.attack
-            .load()
-            .clamp(*ATTACK_RANGE.start(), *ATTACK_RANGE.end())
-    }
-
-    fn get_decay_delay(&self) -> f32 {
-        self.decay_delay
-            .load()
-            .clamp(*DECAY_DELAY_RANGE.start(), *DECAY_DELAY_RANGE.end())
-    }
-
-    fn get_decay(&self) -> f32 {
-        self.decay
-            .load()
-            .clamp(*DECAY_RANGE.start(), *DECAY_RANGE.end())
-    }
-
-    fn get_sustain(&self) -> f32 {
-        self.sustain
-            .load()
-            .clamp(*SUSTAIN_RANGE.start(), *SUSTAIN_RANGE.end())
-    }
-
-    fn get_release(&self) -> f32 {
-        self.release
-            .load()
-            .clamp(*RELEASE_RANGE.start(), *RELEASE_RANGE.end())
-    }
+impl StereoMode {
+    pub const ALL: [StereoMode; 4] = [
+        StereoMode::Mono,
+        StereoMode::ArmTip,
+        StereoMode::HorizontalVertical,
+        StereoMode::MidSide,
+    ];
 }
 
-const LOWPASS_FREQ: f32 = 10000f32;
-
 fn get_lengths(center_length: f32, chaoticity: f32) -> Vec2 {
     let b = center_length / (1f32 + chaoticity / 2f32);
     let c = b * chaoticity;
@@ -112,42 +124,53 @@ fn get_lengths(center_length: f32, chaoticity: f32) -> Vec2 {
 
 #[derive(Clone)]
 pub struct Synth {
-    midi_events: MidiChannel,
+    messages: MessageChannel,
+    /// messages for the next `play` call, with their frame offsets into it. sorted by offset
+    scheduled: VecDeque<(usize, Message)>,
+    /// frame of the block being rendered
+    position: Arc<AtomicCell<usize>>,
 
     simulator: Simulator,
     note_event: Option<NoteEvent>,
     params: Arc<Params>,
-    lowpass: (u32, biquad::DirectForm1<f32>),
+    /// what the sound is generated from
+    smoothed: SmoothedParams,
+    programs: Arc<Programs>,
+    /// a program change that couldn't be made yet, tried again every block
+    pending_program: Option<usize>,
+    filter: FilterSection,
+    dc_blockers: [DcBlocker; 2],
+    normalizer: Normalizer,
+    lfos: [Lfo; NUM_LFOS],
+    chaos: ChaosPendulum,
+    /// inputs to the modulation matrix
+    sources: ModSources,
+    /// of the host, in beats per minute. for tempo synced lfos
+    tempo: f32,
     center_length: f32,
     sample_rate: u32,
 }
 
 impl Synth {
-    pub fn new(midi_events: MidiChannel) -> Self {
+    pub fn new(messages: MessageChannel) -> Self {
         let sample_rate = 44100;
         Self {
-            midi_events,
+            messages,
+            // room for a busy block of events without allocating in the audio thread
+            scheduled: VecDeque::with_capacity(1024),
+            position: Arc::new(AtomicCell::new(0)),
             note_event: None,
-            params: Arc::new(Params {
-                chaoticity: 0.5f32.into(),
-                attack: 0.1f32.into(),
-                decay_delay: 0.5f32.into(),
-                decay: 0.1f32.into(),
-                sustain: 0.5f32.into(),
-                release: 0.1f32.into(),
-            }),
-            lowpass: (
-                0, //< to make sure it is recalculated
-                biquad::DirectForm1::<f32>::new(
-                    biquad::Coefficients::<f32>::from_params(
-                        biquad::Type::LowPass,
-                        sample_rate.hz(),
-                        LOWPASS_FREQ.min(sample_rate as f32 / 2.001f32).hz(),
-                        biquad::Q_BUTTERWORTH_F32,
-                    )
-                    .unwrap(),
-                ),
-            ),
+            params: Arc::new(Params::default()),
+            smoothed: SmoothedParams::default(),
+            programs: Arc::new(Programs::new(factory_presets())),
+            pending_program: None,
+            filter: FilterSection::default(),
+            dc_blockers: Default::default(),
+            normalizer: Normalizer::default(),
+            lfos: std::array::from_fn(|i| Lfo::new(i as u64)),
+            chaos: ChaosPendulum::default(),
+            sources: ModSources::default(),
+            tempo: 120.,
             simulator: Simulator {
                 pendulum: Pendulum {
                     // higher gravity. for better precision. (is it really?)
@@ -166,42 +189,245 @@ impl Synth {
         self.params.clone()
     }
 
-    fn calculate_energy(&self) -> (f32, f32) {
+    pub fn get_programs(&self) -> Arc<Programs> {
+        self.programs.clone()
+    }
+
+    /// how far into its block `play` is, 0 between calls. for timing events that arrive from
+    /// other threads while it runs, by scheduling them at the same frame of the next block
+    pub fn get_position(&self) -> Arc<AtomicCell<usize>> {
+        self.position.clone()
+    }
+
+    /// for hosts that have a tempo. otherwise 120 bpm is used
+    pub fn set_tempo(&mut self, bpm: f32) {
+        if bpm > 0. {
+            self.tempo = bpm;
+        }
+    }
+
+    /// the summed output of all lfos, advancing them one sample
+    fn run_lfos(&mut self) -> Modulation {
+        let mut modulation = Modulation::default();
+        for (index, lfo) in self.lfos.iter_mut().enumerate() {
+            let settings = self.smoothed.get_lfo_settings(index);
+            let value = lfo.run(&settings, self.tempo, self.sample_rate);
+            self.sources.lfos[index] = value;
+            modulation.add(settings.destination, value * settings.depth);
+        }
+        modulation
+    }
+
+    /// handle `message` `frame` frames into the next `play` call. for events that come with an
+    /// offset into the block, like midi from a plugin host. messages from the channel are handled
+    /// at the start of it
+    pub fn schedule(&mut self, frame: usize, message: Message) {
+        // events usually arrive in order, making this an append
+        let index = self.scheduled.partition_point(|&(f, _)| f <= frame);
+        self.scheduled.insert(index, (frame, message));
+    }
+
+    /// handle the scheduled messages that are due. returns true if any were
+    fn handle_scheduled(&mut self, frame: usize) -> bool {
+        let mut handled = false;
+        while self.scheduled.front().is_some_and(|&(f, _)| f <= frame) {
+            if let Some((_, message)) = self.scheduled.pop_front() {
+                self.handle_message(message);
+                handled = true;
+            }
+        }
+        handled
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Midi(midi) => self.handle_midi(midi),
+            Message::SetParam(param, value) => self.params.set(param, value),
+        }
+    }
+
+    fn handle_midi(&mut self, message: MidiMessage<'static>) {
+        match message {
+            wmidi::MidiMessage::NoteOn(_, note, velocity) => {
+                let freq = self.params.get_note_freq(note);
+                if freq <= 0f32 {
+                    // not mapped by the current tuning
+                    return;
+                }
+                let norm_vel = self
+                    .smoothed
+                    .get_choice(Param::VelocityCurve, &VelocityCurve::ALL)
+                    .apply(u7_to_f32(velocity));
+                self.sources.velocity = norm_vel;
+                self.sources.key = ((u8::from(note) as f32 - 60.) / 64.).clamp(-1., 1.);
+                // TODO make g a constant
+                // TODO calculate length better. do a few components of the large amplitude equation
+                self.center_length = (1f32 / freq / 2f32 / PI).powi(2) * self.simulator.pendulum.g;
+                self.note_event = Some(NoteEvent {
+                    note,
+                    state: NoteState::Pressed(0),
+                    velocity: norm_vel,
+                    frequency: freq,
+                });
+                self.filter.trigger();
+                for (index, lfo) in self.lfos.iter_mut().enumerate() {
+                    if self.smoothed.get_lfo_settings(index).mode == LfoMode::PerVoice {
+                        lfo.trigger();
+                    }
+                }
+            }
+            wmidi::MidiMessage::NoteOff(_, note, _) => {
+                if let Some(NoteEvent {
+                    note: held_note,
+                    ref mut state,
+                    ..
+                }) = self.note_event
+                {
+                    if note == held_note {
+                        if let NoteState::Pressed(_) = *state {
+                            *state = NoteState::Released;
+                            self.filter.release();
+                        }
+                    }
+                }
+            }
+            wmidi::MidiMessage::ControlChange(
+                _,
+                wmidi::ControlFunction::MODULATION_WHEEL,
+                value,
+            ) => {
+                self.sources.mod_wheel = u7_to_f32(value);
+            }
+            wmidi::MidiMessage::ChannelPressure(_, value) => {
+                self.sources.aftertouch = u7_to_f32(value);
+            }
+            wmidi::MidiMessage::PolyphonicKeyPressure(_, note, value)
+                if self.note_event.as_ref().map(|event| event.note) == Some(note) =>
+            {
+                self.sources.aftertouch = u7_to_f32(value);
+            }
+            wmidi::MidiMessage::PitchBendChange(_, value) => {
+                // 8192 is the center
+                self.sources.pitch_bend =
+                    ((u16::from(value) as f32 - 8192.) / 8192.).clamp(-1., 1.);
+            }
+            wmidi::MidiMessage::ProgramChange(_, program) => {
+                self.pending_program = Some(u8::from(program) as usize);
+                self.change_program();
+            }
+            _ => {}
+        }
+    }
+
+    fn change_program(&mut self) {
+        if let Some(index) = self.pending_program {
+            if self.programs.try_select(index, &self.params) {
+                self.pending_program = None;
+            }
+        }
+    }
+
+    /// left and right samples, before filtering
+    fn get_stereo_sample(&self) -> (f32, f32) {
+        let (left, right) = match self
+            .smoothed
+            .get_choice(Param::StereoMode, &StereoMode::ALL)
+        {
+            StereoMode::Mono => {
+                let mix = Tap::ALL
+                    .iter()
+                    .map(|&tap| (tap, self.smoothed.get(Param::tap_gain(tap))))
+                    .filter(|&(_, gain)| gain != 0f32)
+                    .map(|(tap, gain)| gain * self.simulator.get_tap(tap))
+                    .sum();
+                (mix, mix)
+            }
+            StereoMode::ArmTip => (
+                self.simulator.get_normalized_arm_x(),
+                self.simulator.get_normalized_x(),
+            ),
+            StereoMode::HorizontalVertical => (
+                self.simulator.get_normalized_x(),
+                // 0 at rest like the horizontal position, so silence stays silent
+                self.simulator.get_normalized_y(),
+            ),
+            StereoMode::MidSide => {
+                let sines = self.simulator.get_angle_sines();
+                (sines.x, sines.y)
+            }
+        };
+        let mid = (left + right) / 2.;
+        let side = (left - right) / 2. * self.smoothed.get(Param::StereoWidth);
+        (mid + side, mid - side)
+    }
+
+    /// octaves from the key tracking center to the current note
+    fn key_octaves(&self) -> f32 {
+        self.note_event.as_ref().map_or(0., |event| {
+            (u8::from(event.note) as f32 - self.smoothed.get(Param::KeyTrackingCenter)) / 12.
+        })
+    }
+
+    /// an envelope time parameter, scaled by its key tracking amount
+    fn envelope_time(&self, param: Param, key_tracking: Param) -> f32 {
+        self.smoothed.get(param) * (-self.smoothed.get(key_tracking) * self.key_octaves()).exp2()
+    }
+
+    /// `energy_modulation` is relative to the envelope's energy
+    fn calculate_energy(&self, energy_modulation: f32) -> (f32, f32) {
         if let Some(event) = &self.note_event {
             const VELOCITY_WEIGHT: f32 = 0.5;
             const_assert!(VELOCITY_WEIGHT >= 0. && VELOCITY_WEIGHT <= 2.);
-            let length = get_lengths(self.center_length, self.params.chaoticity.load());
+            let length = self.simulator.pendulum.length;
             let Pendulum { g, mass, .. } = self.simulator.pendulum;
             let mass_sum = mass.x + mass.y;
-            let desired_potential =
-                g * VELOCITY_WEIGHT * event.velocity * (mass_sum * length.x + mass.y * length.y);
+            let velocity_energy = self.smoothed.get(Param::VelocityEnergy);
+            let desired_potential = g
+                * VELOCITY_WEIGHT
+                * (1. - velocity_energy + velocity_energy * event.velocity)
+                * (mass_sum * length.x + mass.y * length.y)
+                * (1. + energy_modulation).max(0.);
             dbg_value!(desired_potential);
             match event.state {
                 NoteState::Pressed(elapsed) => {
                     let elapsed_seconds = elapsed as f32 / self.sample_rate as f32;
                     dbg_value!(elapsed_seconds);
-                    dbg_value!(self.params.get_decay_delay());
-                    if elapsed_seconds < self.params.get_decay_delay() {
+                    dbg_value!(self.smoothed.get(Param::DecayDelay));
+                    if elapsed_seconds < self.smoothed.get(Param::DecayDelay) {
                         dbg_value("state", 0.);
-                        let attack = 1. / (self.params.get_attack() * PARAM_DIV + 1.);
+                        let attack_scale = (1.
+                            - self.smoothed.get(Param::VelocityAttack) * event.velocity)
+                            .max(0.);
+                        let attack = 1.
+                            / (self.envelope_time(Param::Attack, Param::KeyTrackingAttack)
+                                * attack_scale
+                                * PARAM_DIV
+                                + 1.);
                         dbg_value!(attack);
                         (desired_potential, attack)
                     } else {
                         // TODO get the current energy here instead of desired_potential?
                         dbg_value("state", 1.);
                         (
-                            desired_potential * self.params.get_sustain(),
-                            1. / (self.params.get_decay() * PARAM_DIV + 1.),
+                            desired_potential * self.smoothed.get(Param::Sustain),
+                            1. / (self.envelope_time(Param::Decay, Param::KeyTrackingDecay)
+                                * PARAM_DIV
+                                + 1.),
                         )
                     }
                 }
                 NoteState::Released => {
                     dbg_value("state", 2.);
-                    (0., 1. / (self.params.get_release() * PARAM_DIV + 1.))
+                    (
+                        0.,
+                        1. / (self.envelope_time(Param::Release, Param::KeyTrackingRelease)
+                            * PARAM_DIV
+                            + 1.),
+                    )
                 }
             }
         } else {
-            (0., 1. - self.params.get_release())
+            (0., 1. - self.smoothed.get(Param::Release))
         }
     }
 }
@@ -215,99 +441,119 @@ impl SynthPlayer for Synth {
         debug_assert!(sample_rate > 0);
         // TODO check if sample_rate has changed, and recalculate stuff?
         self.sample_rate = sample_rate;
-        let chaoticity = self
-            .params
-            .chaoticity
-            .load()
-            .clamp(*CHAOTICITY_RANGE.start(), *CHAOTICITY_RANGE.end());
-        // pump midi messages
-        for message in self.midi_events.try_iter() {
-            match message {
-                wmidi::MidiMessage::NoteOn(_, note, velocity) => {
-                    let norm_vel = u7_to_f32(velocity);
-                    // TODO make g a constant
-                    // TODO calculate length better. do a few components of the large amplitude equation
-                    self.center_length =
-                        (1f32 / note.to_freq_f32() / 2f32 / PI).powi(2) * self.simulator.pendulum.g;
-                    self.note_event = Some(NoteEvent {
-                        note,
-                        state: NoteState::Pressed(0),
-                        velocity: norm_vel,
-                    });
-                }
-                wmidi::MidiMessage::NoteOff(_, note, _) => {
-                    if let Some(NoteEvent {
-                        note: held_note,
-                        ref mut state,
-                        ..
-                    }) = self.note_event
-                    {
-                        if note == held_note {
-                            if let NoteState::Pressed(_) = *state {
-                                *state = NoteState::Released;
-                            }
-                        }
-                    }
-                }
-                wmidi::MidiMessage::ControlChange(
-                    _,
-                    wmidi::ControlFunction::MODULATION_WHEEL,
-                    value,
-                ) => {
-                    let norm_value = u7_to_f32(value);
-                    let chaoticity = CHAOTICITY_RANGE.start()
-                        + norm_value * (CHAOTICITY_RANGE.end() - CHAOTICITY_RANGE.start());
-                    self.params.chaoticity.store(chaoticity);
-                }
-                _ => {}
-            }
+        while let Ok(message) = self.messages.try_recv() {
+            self.handle_message(message);
         }
+        self.change_program();
+        self.smoothed.set_targets(&self.params, sample_rate);
 
-        if self.lowpass.0 != sample_rate {
-            self.lowpass = (
+        // produce sound
+        for (index, frame) in output.chunks_exact_mut(channels).enumerate() {
+            self.position.store(index);
+            if self.handle_scheduled(index) {
+                self.smoothed.set_targets(&self.params, sample_rate);
+            }
+            self.smoothed.update();
+            self.sources.filter_envelope = self.filter.envelope_level();
+            self.sources.arm_angle = self.simulator.get_tap(Tap::Angle1);
+            self.sources.energy = self.simulator.get_tap(Tap::KineticEnergy).clamp(0., 1.);
+            self.sources.tip_angle = self.simulator.get_tap(Tap::Angle2);
+            self.sources.momentum = self.simulator.get_tap(Tap::Momentum1).clamp(-1., 1.);
+            self.chaos.run(
+                self.smoothed.get(Param::ChaosRate),
+                self.smoothed.get(Param::ChaosAmount),
                 sample_rate,
-                biquad::DirectForm1::<f32>::new(
-                    biquad::Coefficients::<f32>::from_params(
-                        // TODO use singlepole instead?
-                        biquad::Type::LowPass,
-                        //biquad::Type::SinglePoleLowPass,
-                        sample_rate.hz(),
-                        LOWPASS_FREQ.min(sample_rate as f32 / 2.001f32).hz(),
-                        biquad::Q_BUTTERWORTH_F32,
-                    )
-                    .unwrap(),
-                ),
             );
-        }
+            self.sources.chaos = [self.chaos.x(), self.chaos.y()];
+            // before the lfos, so the matrix modulates their rate and depth for this sample. the
+            // lfo sources it reads are from the previous sample, like the pendulum ones
+            self.smoothed.modulate(&self.sources);
+            let modulation = self.run_lfos();
+            let mut filter_settings = self.smoothed.get_filter_settings();
+            filter_settings.cutoff *= modulation.cutoff_octaves.exp2();
+            let saturation = self
+                .smoothed
+                .get_choice(Param::Saturation, &Saturation::ALL);
+            let drive = db_to_gain(self.smoothed.get(Param::Drive));
+            let output_gain = db_to_gain(self.smoothed.get(Param::OutputGain));
+            let pan = self.smoothed.get(Param::Pan);
 
-        // TODO make the lengths the same, and change the mass instead?
-        // TODO is it perhaps only the first length that should be used to calculate the center of mass?
-        // TODO figure this out
-        let length = get_lengths(self.center_length, chaoticity);
-        self.simulator.pendulum.length = length;
-        // TODO recalculate the momenta depending on the chaoticity?
+            // TODO make the lengths the same, and change the mass instead?
+            // TODO is it perhaps only the first length that should be used to calculate the center of mass?
+            // TODO figure this out
+            let chaoticity_range = &Param::Chaoticity.descriptor().range;
+            let velocity_chaoticity = self.note_event.as_ref().map_or(0., |event| {
+                event.velocity * self.smoothed.get(Param::VelocityChaoticity)
+            });
+            let key_chaoticity =
+                self.smoothed.get(Param::KeyTrackingChaoticity) * self.key_octaves();
+            let chaoticity = (self.smoothed.get(Param::Chaoticity)
+                + modulation.chaoticity
+                + velocity_chaoticity
+                + key_chaoticity)
+                .clamp(*chaoticity_range.start(), *chaoticity_range.end());
+            // the frequency goes with the inverse square root of the length
+            let center_length =
+                self.center_length * (-2. * modulation.pitch_semitones / 12.).exp2();
+            let length = get_lengths(center_length, chaoticity);
+            self.simulator.pendulum.length = length;
+            // TODO recalculate the momenta depending on the chaoticity?
 
-        // produce sound
-        for frame in output.chunks_exact_mut(channels) {
-            let a = self.simulator.get_normalized_x();
-            let lowpassed = self.lowpass.1.run(a);
-            let clipped = lowpassed.clamp(-1f32, 1f32);
-            for sample in frame.iter_mut() {
-                *sample = clipped;
+            let (frequency, velocity) = self
+                .note_event
+                .as_ref()
+                .map_or((filter::KEY_TRACKING_CENTER_FREQ, 0f32), |event| {
+                    (event.frequency, event.velocity)
+                });
+            let (left, right) = self.filter.run(
+                self.get_stereo_sample(),
+                &filter_settings,
+                frequency,
+                velocity,
+                sample_rate,
+            );
+            let [left_dc_blocker, right_dc_blocker] = &mut self.dc_blockers;
+            let (left, right) = (
+                left_dc_blocker.run(left, sample_rate),
+                right_dc_blocker.run(right, sample_rate),
+            );
+            // before the saturation, so it distorts the same regardless of the level
+            let (left, right) = self.normalizer.run(
+                (left, right),
+                self.smoothed.get(Param::Normalization),
+                sample_rate,
+            );
+            // balance, the center leaves both channels as they are
+            let [left, right] = [(left, 1. - pan), (right, 1. + pan)].map(|(sample, balance)| {
+                (saturation.run(sample * drive) * output_gain * balance.min(1.)).clamp(-1f32, 1f32)
+            });
+            match frame {
+                [mono] => *mono = (left + right) / 2.,
+                [l, r, rest @ ..] => {
+                    *l = left;
+                    *r = right;
+                    for sample in rest {
+                        *sample = (left + right) / 2.;
+                    }
+                }
+                [] => {}
             }
 
-            let (energy, p) = self.calculate_energy();
+            let (energy, p) = self.calculate_energy(modulation.energy);
             self.simulator.update(1. / sample_rate as f32, energy, p);
             if let Some(event) = &mut self.note_event {
                 event.state.update(1);
             }
         }
+        // anything scheduled past the end of the block
+        self.handle_scheduled(usize::MAX);
+        self.position.store(0);
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Synth, SynthPlayer};
+    use super::{Message, Param, StereoMode, Synth, SynthPlayer, Tap};
     use crossbeam::channel;
 
     #[test]
@@ -318,4 +564,82 @@ mod test {
         synth.play(48000, 2, &mut data);
         assert_eq!([0f32; 512], data);
     }
+
+    /// every way of mapping the pendulum to the output is 0 at rest
+    #[test]
+    fn silence_in_every_mode() {
+        for (index, mode) in StereoMode::ALL.iter().enumerate() {
+            for tap in Tap::ALL {
+                let (tx, rx) = channel::unbounded();
+                let mut synth = Synth::new(rx);
+                tx.send(Message::SetParam(Param::StereoMode, index as f32))
+                    .unwrap();
+                for other in Tap::ALL {
+                    let gain = if other == tap { 1. } else { 0. };
+                    tx.send(Message::SetParam(Param::tap_gain(other), gain))
+                        .unwrap();
+                }
+                let mut data = [0f32; 512];
+                synth.play(48000, 2, &mut data);
+                assert!(data.iter().all(|&sample| sample == 0.), "{mode:?} {tap:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn set_param_message() {
+        let (tx, rx) = channel::bounded(1);
+        let mut synth = Synth::new(rx);
+        tx.send(Message::SetParam(Param::Drive, 12.)).unwrap();
+        let mut data = [0f32; 512];
+        synth.play(48000, 2, &mut data);
+        assert_eq!(synth.get_params().get(Param::Drive), 12.);
+    }
+
+    #[test]
+    fn key_tracked_chaoticity() {
+        let (tx, rx) = channel::unbounded();
+        let mut synth = Synth::new(rx);
+        tx.send(Message::SetParam(Param::KeyTrackingChaoticity, 0.2))
+            .unwrap();
+        // past the parameter smoothing
+        let mut data = [0f32; 2 * 48000];
+        synth.play(48000, 2, &mut data);
+        let mut chaoticity = |note| {
+            tx.send(wmidi::MidiMessage::NoteOn(wmidi::Channel::Ch1, note, wmidi::U7::MAX).into())
+                .unwrap();
+            let mut data = [0f32; 64];
+            synth.play(48000, 2, &mut data);
+            let length = synth.simulator.pendulum.length;
+            length.y / length.x
+        };
+        let default = Param::Chaoticity.descriptor().default;
+        // two octaves around the center key
+        assert!((chaoticity(wmidi::Note::C4) - default).abs() < 1e-4);
+        assert!((chaoticity(wmidi::Note::C6) - (default + 0.4)).abs() < 1e-4);
+        assert!((chaoticity(wmidi::Note::C2) - (default - 0.4)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn scheduled_note() {
+        let (_tx, rx) = channel::bounded(1);
+        let mut synth = Synth::new(rx);
+        let offset = 256;
+        synth.schedule(
+            offset,
+            wmidi::MidiMessage::NoteOn(
+                wmidi::Channel::Ch1,
+                wmidi::Note::C4,
+                wmidi::U7::from_u8_lossy(100),
+            )
+            .into(),
+        );
+        synth.schedule(0, Message::SetParam(Param::Drive, 12.));
+        let mut data = [0f32; 2 * 4096];
+        synth.play(48000, 2, &mut data);
+        assert_eq!(synth.get_params().get(Param::Drive), 12.);
+        let (before, after) = data.split_at(2 * offset);
+        assert!(before.iter().all(|&sample| sample == 0.));
+        assert!(after.iter().any(|&sample| sample != 0.));
+    }
 }
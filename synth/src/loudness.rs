@@ -0,0 +1,93 @@
+// automatic gain control. how loud the pendulum is depends a lot on the chaoticity, energy and
+// pitch, so this rides the gain towards a target level to keep patches even across the keyboard
+
+use crate::saturation::db_to_gain;
+
+/// the level everything is brought towards, as rms
+const TARGET_RMS: f32 = 0.2;
+/// quiet signals aren't boosted more than this, so decays and silence don't turn into noise
+const MAX_GAIN_DB: f32 = 12.;
+/// time constant of the level measurement. slow enough that the gain doesn't follow the
+/// pendulum's swings
+const TIME_SECONDS: f32 = 0.3;
+/// below this the level isn't measured, so the gain is kept through silence and the next note
+/// starts out with about the right level instead of fully boosted
+const GATE: f32 = 1e-3;
+
+#[derive(Clone)]
+pub(crate) struct Normalizer {
+    /// running mean of the squared signal, over both channels
+    mean_square: f32,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self {
+            mean_square: TARGET_RMS * TARGET_RMS,
+        }
+    }
+}
+
+impl Normalizer {
+    /// `amount` in 0..=1 blends between no gain change and full normalization
+    pub fn run(&mut self, (left, right): (f32, f32), amount: f32, sample_rate: u32) -> (f32, f32) {
+        let square = (left * left + right * right) / 2.;
+        if square > GATE * GATE {
+            let coefficient = 1. - (-1. / (TIME_SECONDS * sample_rate as f32)).exp();
+            self.mean_square += (square - self.mean_square) * coefficient;
+        }
+        if amount <= 0. {
+            return (left, right);
+        }
+        let floor = TARGET_RMS / db_to_gain(MAX_GAIN_DB);
+        let gain = (TARGET_RMS / self.mean_square.sqrt().max(floor)).powf(amount.min(1.));
+        (left * gain, right * gain)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Message, Param, Synth, SynthPlayer};
+    use crossbeam::channel;
+
+    /// rms of the sustained part of a note
+    fn note_rms(note: wmidi::Note, chaoticity: f32, normalization: f32) -> f32 {
+        let (tx, rx) = channel::unbounded();
+        let mut synth = Synth::new(rx);
+        tx.send(Message::SetParam(Param::Chaoticity, chaoticity))
+            .unwrap();
+        tx.send(Message::SetParam(Param::Normalization, normalization))
+            .unwrap();
+        tx.send(wmidi::MidiMessage::NoteOn(wmidi::Channel::Ch1, note, wmidi::U7::MAX).into())
+            .unwrap();
+        let sample_rate = 48000;
+        let mut data = vec![0f32; 2 * sample_rate];
+        synth.play(sample_rate as u32, 2, &mut data);
+        // skip the attack and the gain settling
+        let sustained = &data[data.len() / 2..];
+        (sustained.iter().map(|x| x * x).sum::<f32>() / sustained.len() as f32).sqrt()
+    }
+
+    /// difference between the loudest and quietest note of a grid of pitches and chaoticities,
+    /// in dB
+    fn spread(normalization: f32) -> f32 {
+        let mut levels = vec![];
+        for note in [wmidi::Note::C2, wmidi::Note::C4, wmidi::Note::C6] {
+            for chaoticity in [0.2, 0.5, 0.9] {
+                levels.push(20. * note_rms(note, chaoticity, normalization).log10());
+            }
+        }
+        let (min, max) = levels.iter().fold((f32::MAX, f32::MIN), |(min, max), &l| {
+            (min.min(l), max.max(l))
+        });
+        max - min
+    }
+
+    #[test]
+    fn level_spread() {
+        let raw = spread(0.);
+        let normalized = spread(1.);
+        assert!(normalized < 3., "{normalized} dB");
+        assert!(normalized < raw / 2., "{normalized} dB, {raw} dB without");
+    }
+}
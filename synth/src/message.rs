@@ -0,0 +1,23 @@
+use crate::Param;
+use crossbeam::channel;
+use wmidi::MidiMessage;
+
+/// everything sent to the synth from other threads
+#[derive(Debug, Clone)]
+pub enum Message {
+    Midi(MidiMessage<'static>),
+    /// choice parameters take the index of the choice
+    SetParam(Param, f32),
+}
+
+impl From<MidiMessage<'static>> for Message {
+    fn from(msg: MidiMessage<'static>) -> Self {
+        Message::Midi(msg)
+    }
+}
+
+/// room for a few whole sounds sent with `State::send` between two audio blocks
+pub const CHANNEL_CAPACITY: usize = 1024;
+
+pub type MessageChannel = channel::Receiver<Message>;
+pub type MessageSender = channel::Sender<Message>;
@@ -0,0 +1,279 @@
+// routes modulation sources to parameters. every slot is a set of parameters, so routings are
+// stored in patches and automatable like everything else
+
+use crate::{Param, NUM_LFOS};
+
+pub const NUM_MOD_SLOTS: usize = 8;
+
+/// the gui shows this section as a grid
+pub(crate) const MOD_SECTION: &str = "modulation";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ModSource {
+    None,
+    Velocity,
+    /// the note, -1..=1 around middle c
+    Key,
+    ModWheel,
+    Aftertouch,
+    PitchBend,
+    Lfo1,
+    Lfo2,
+    FilterEnvelope,
+    /// angle of the first arm
+    ArmAngle,
+    /// kinetic energy of the pendulum
+    Energy,
+    /// position of the chaos pendulum's tip
+    ChaosX,
+    ChaosY,
+    /// angle of the second arm
+    TipAngle,
+    /// momentum of the first arm
+    Momentum,
+}
+
+impl ModSource {
+    pub const ALL: [ModSource; 15] = [
+        ModSource::None,
+        ModSource::Velocity,
+        ModSource::Key,
+        ModSource::ModWheel,
+        ModSource::Aftertouch,
+        ModSource::PitchBend,
+        ModSource::Lfo1,
+        ModSource::Lfo2,
+        ModSource::FilterEnvelope,
+        ModSource::ArmAngle,
+        ModSource::Energy,
+        ModSource::ChaosX,
+        ModSource::ChaosY,
+        ModSource::TipAngle,
+        ModSource::Momentum,
+    ];
+}
+
+/// shapes the source value. all curves keep the sign and map -1, 0 and 1 to themselves
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ModCurve {
+    Linear,
+    Exponential,
+    Logarithmic,
+    SCurve,
+}
+
+impl ModCurve {
+    pub const ALL: [ModCurve; 4] = [
+        ModCurve::Linear,
+        ModCurve::Exponential,
+        ModCurve::Logarithmic,
+        ModCurve::SCurve,
+    ];
+
+    pub fn apply(self, x: f32) -> f32 {
+        let a = x.abs().min(1.);
+        let shaped = match self {
+            ModCurve::Linear => a,
+            ModCurve::Exponential => a * a,
+            ModCurve::Logarithmic => a.sqrt(),
+            ModCurve::SCurve => a * a * (3. - 2. * a),
+        };
+        shaped.copysign(x)
+    }
+}
+
+pub(crate) const NUM_MOD_DESTINATIONS: usize = 44;
+
+/// the parameters that can be modulated, in the order of the destination choices after none. the
+/// names are the parameter names, with the section for clarity.
+/// master tune only applies at note on and the center key is a note, so they aren't here. new ones
+/// go at the end, so saved routings keep their meaning
+pub(crate) const MOD_DESTINATIONS: [(Param, &str); NUM_MOD_DESTINATIONS] = [
+    (Param::Chaoticity, "chaoticity"),
+    (Param::Attack, "attack"),
+    (Param::DecayDelay, "decay delay"),
+    (Param::Decay, "decay"),
+    (Param::Sustain, "sustain"),
+    (Param::Release, "release"),
+    (Param::StereoWidth, "stereo width"),
+    (Param::TapAngle1, "tap angle 1"),
+    (Param::TapAngle2, "tap angle 2"),
+    (Param::TapTipX, "tap tip x"),
+    (Param::TapTipY, "tap tip y"),
+    (Param::TapAngularVelocity1, "tap angular velocity 1"),
+    (Param::TapAngularVelocity2, "tap angular velocity 2"),
+    (Param::TapMomentum1, "tap momentum 1"),
+    (Param::TapMomentum2, "tap momentum 2"),
+    (Param::TapKineticEnergy, "tap kinetic energy"),
+    (Param::FilterCutoff, "filter cutoff"),
+    (Param::FilterResonance, "filter resonance"),
+    (Param::FilterEnvelopeAmount, "filter envelope amount"),
+    (Param::FilterAttack, "filter attack"),
+    (Param::FilterDecay, "filter decay"),
+    (Param::FilterSustain, "filter sustain"),
+    (Param::FilterRelease, "filter release"),
+    (Param::FilterKeyTracking, "filter key tracking"),
+    (Param::FilterVelocity, "filter velocity"),
+    (Param::Drive, "drive"),
+    (Param::OutputGain, "output gain"),
+    (Param::Lfo1Rate, "lfo 1 rate"),
+    (Param::Lfo1Phase, "lfo 1 phase"),
+    (Param::Lfo1Depth, "lfo 1 depth"),
+    (Param::Lfo2Rate, "lfo 2 rate"),
+    (Param::Lfo2Phase, "lfo 2 phase"),
+    (Param::Lfo2Depth, "lfo 2 depth"),
+    (Param::Pan, "pan"),
+    (Param::ChaosRate, "chaos rate"),
+    (Param::ChaosAmount, "chaos amount"),
+    (Param::VelocityEnergy, "velocity energy"),
+    (Param::VelocityAttack, "velocity attack"),
+    (Param::VelocityChaoticity, "velocity chaoticity"),
+    (Param::KeyTrackingChaoticity, "key tracking chaoticity"),
+    (Param::KeyTrackingAttack, "key tracking attack"),
+    (Param::KeyTrackingDecay, "key tracking decay"),
+    (Param::KeyTrackingRelease, "key tracking release"),
+    (Param::Normalization, "normalization"),
+];
+
+/// the choices of the destination parameters, built from `MOD_DESTINATIONS`
+pub(crate) const MOD_DESTINATION_CHOICES: [&str; NUM_MOD_DESTINATIONS + 1] = {
+    let mut choices = ["none"; NUM_MOD_DESTINATIONS + 1];
+    let mut i = 0;
+    while i < NUM_MOD_DESTINATIONS {
+        choices[i + 1] = MOD_DESTINATIONS[i].1;
+        i += 1;
+    }
+    choices
+};
+
+/// the value of a destination parameter
+pub(crate) fn destination(choice: f32) -> Option<Param> {
+    MOD_DESTINATIONS
+        .get((choice as usize).checked_sub(1)?)
+        .map(|&(param, _)| param)
+}
+
+pub(crate) struct ModSlotParams {
+    pub source: Param,
+    pub destination: Param,
+    pub amount: Param,
+    pub curve: Param,
+}
+
+pub(crate) const MOD_SLOT_PARAMS: [ModSlotParams; NUM_MOD_SLOTS] = [
+    ModSlotParams {
+        source: Param::Mod1Source,
+        destination: Param::Mod1Destination,
+        amount: Param::Mod1Amount,
+        curve: Param::Mod1Curve,
+    },
+    ModSlotParams {
+        source: Param::Mod2Source,
+        destination: Param::Mod2Destination,
+        amount: Param::Mod2Amount,
+        curve: Param::Mod2Curve,
+    },
+    ModSlotParams {
+        source: Param::Mod3Source,
+        destination: Param::Mod3Destination,
+        amount: Param::Mod3Amount,
+        curve: Param::Mod3Curve,
+    },
+    ModSlotParams {
+        source: Param::Mod4Source,
+        destination: Param::Mod4Destination,
+        amount: Param::Mod4Amount,
+        curve: Param::Mod4Curve,
+    },
+    ModSlotParams {
+        source: Param::Mod5Source,
+        destination: Param::Mod5Destination,
+        amount: Param::Mod5Amount,
+        curve: Param::Mod5Curve,
+    },
+    ModSlotParams {
+        source: Param::Mod6Source,
+        destination: Param::Mod6Destination,
+        amount: Param::Mod6Amount,
+        curve: Param::Mod6Curve,
+    },
+    ModSlotParams {
+        source: Param::Mod7Source,
+        destination: Param::Mod7Destination,
+        amount: Param::Mod7Amount,
+        curve: Param::Mod7Curve,
+    },
+    ModSlotParams {
+        source: Param::Mod8Source,
+        destination: Param::Mod8Destination,
+        amount: Param::Mod8Amount,
+        curve: Param::Mod8Curve,
+    },
+];
+
+/// current values of the modulation sources. unipolar sources are 0..=1, bipolar -1..=1
+#[derive(Clone, Default, Debug)]
+pub(crate) struct ModSources {
+    pub velocity: f32,
+    pub key: f32,
+    pub mod_wheel: f32,
+    pub aftertouch: f32,
+    pub pitch_bend: f32,
+    pub lfos: [f32; NUM_LFOS],
+    pub filter_envelope: f32,
+    pub arm_angle: f32,
+    pub energy: f32,
+    pub chaos: [f32; 2],
+    pub tip_angle: f32,
+    pub momentum: f32,
+}
+
+impl ModSources {
+    pub fn get(&self, source: ModSource) -> f32 {
+        match source {
+            ModSource::None => 0.,
+            ModSource::Velocity => self.velocity,
+            ModSource::Key => self.key,
+            ModSource::ModWheel => self.mod_wheel,
+            ModSource::Aftertouch => self.aftertouch,
+            ModSource::PitchBend => self.pitch_bend,
+            ModSource::Lfo1 => self.lfos[0],
+            ModSource::Lfo2 => self.lfos[1],
+            ModSource::FilterEnvelope => self.filter_envelope,
+            ModSource::ArmAngle => self.arm_angle,
+            ModSource::Energy => self.energy,
+            ModSource::ChaosX => self.chaos[0],
+            ModSource::ChaosY => self.chaos[1],
+            ModSource::TipAngle => self.tip_angle,
+            ModSource::Momentum => self.momentum,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{destination, ModCurve, MOD_DESTINATIONS, MOD_SLOT_PARAMS};
+
+    #[test]
+    fn destinations() {
+        let choices = MOD_SLOT_PARAMS[0].destination.descriptor().choices;
+        assert_eq!(destination(0.), None);
+        for (i, (param, name)) in MOD_DESTINATIONS.into_iter().enumerate() {
+            assert!(!MOD_DESTINATIONS[..i].iter().any(|&(p, _)| p == param));
+            assert_eq!(destination((i + 1) as f32), Some(param));
+            assert!(!param.descriptor().is_choice());
+            assert_eq!(choices[i + 1], name);
+            assert!(name.ends_with(param.descriptor().name));
+        }
+    }
+
+    #[test]
+    fn curves() {
+        for curve in ModCurve::ALL {
+            for x in [-1f32, 0., 1.] {
+                assert_eq!(curve.apply(x), x, "{curve:?}");
+            }
+            assert!(curve.apply(-0.5) < 0.);
+        }
+    }
+}
@@ -0,0 +1,1096 @@
+// every synth parameter is described once here. the gui, plugin hosts and presets are all driven by this table
+
+use crossbeam::atomic::AtomicCell;
+use std::{ops::RangeInclusive, sync::Mutex};
+
+use crate::{
+    mod_matrix::{MOD_DESTINATION_CHOICES, MOD_SECTION, NUM_MOD_DESTINATIONS},
+    tuning::{self, Tuning},
+    Tap,
+};
+
+/// how a parameter's range is mapped to sliders and normalized host values
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Curve {
+    Linear,
+    /// equal ratios get equal distances. the range must be positive
+    Logarithmic,
+}
+
+/// what values randomizing may pick for a parameter
+pub enum Randomize {
+    /// left alone, like global settings that aren't part of the sound's character
+    Never,
+    Full,
+    /// the part of the range that is musically safe
+    Within(RangeInclusive<f32>),
+}
+
+pub struct ParamDescriptor {
+    /// stable identifier used when storing parameters. never change it once released
+    pub id: &'static str,
+    pub name: &'static str,
+    /// gui section the parameter is shown in. empty for the top level
+    pub section: &'static str,
+    pub range: RangeInclusive<f32>,
+    pub default: f32,
+    pub unit: &'static str,
+    pub curve: Curve,
+    /// names of the choices of a choice parameter, whose value is the index of the choice.
+    /// empty for continuous parameters
+    pub choices: &'static [&'static str],
+    /// formats continuous values, without the unit
+    pub format: fn(f32) -> String,
+    pub random: Randomize,
+    /// time constant in seconds of the glide to new values. 0 applies changes immediately
+    pub smoothing: f32,
+}
+
+fn format_default(value: f32) -> String {
+    format!("{value:.2}")
+}
+
+fn format_one_decimal(value: f32) -> String {
+    format!("{value:.1}")
+}
+
+fn format_integer(value: f32) -> String {
+    format!("{value:.0}")
+}
+
+const CONTINUOUS: ParamDescriptor = ParamDescriptor {
+    id: "",
+    name: "",
+    section: "",
+    range: 0f32..=1f32,
+    default: 0f32,
+    unit: "",
+    curve: Curve::Linear,
+    choices: &[],
+    format: format_default,
+    random: Randomize::Full,
+    smoothing: 0.02,
+};
+
+impl ParamDescriptor {
+    pub fn is_choice(&self) -> bool {
+        !self.choices.is_empty()
+    }
+
+    /// clamp to the range, rounding choices to the nearest index
+    pub fn clamp(&self, value: f32) -> f32 {
+        let value = if value.is_nan() { self.default } else { value };
+        let value = value.clamp(*self.range.start(), *self.range.end());
+        if self.is_choice() {
+            value.round()
+        } else {
+            value
+        }
+    }
+
+    /// map a value to 0..=1
+    pub fn to_normalized(&self, value: f32) -> f32 {
+        let (start, end) = (*self.range.start(), *self.range.end());
+        let value = self.clamp(value);
+        match self.curve {
+            Curve::Linear => (value - start) / (end - start),
+            Curve::Logarithmic => (value / start).ln() / (end / start).ln(),
+        }
+    }
+
+    /// map 0..=1 to a value in the range
+    pub fn from_normalized(&self, normalized: f32) -> f32 {
+        let (start, end) = (*self.range.start(), *self.range.end());
+        let normalized = normalized.clamp(0., 1.);
+        self.clamp(match self.curve {
+            Curve::Linear => start + normalized * (end - start),
+            Curve::Logarithmic => start * (end / start).powf(normalized),
+        })
+    }
+
+    /// the value as shown to the user, without the unit
+    pub fn format_value(&self, value: f32) -> String {
+        let value = self.clamp(value);
+        if self.is_choice() {
+            self.choices[value as usize].to_string()
+        } else {
+            (self.format)(value)
+        }
+    }
+}
+
+macro_rules! params {
+    ($($(#[$attr:meta])* $param:ident => $descriptor:expr,)*) => {
+        /// identifies a parameter in `Params`. hosts automate parameters by their position here,
+        /// so new ones are only ever added at the end, and none are removed or reordered
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        pub enum Param {
+            $($(#[$attr])* $param,)*
+        }
+
+        pub const NUM_PARAMS: usize = [$(stringify!($param),)*].len();
+
+        impl Param {
+            pub const ALL: [Param; NUM_PARAMS] = [$(Param::$param,)*];
+        }
+
+        static DESCRIPTORS: [ParamDescriptor; NUM_PARAMS] = [$($descriptor,)*];
+    };
+}
+
+// append only, see `Param`
+params! {
+    Chaoticity => ParamDescriptor {
+        id: "chaoticity",
+        name: "chaoticity",
+        range: 0.1f32..=1f32,
+        default: 0.5,
+        // changes the pendulum lengths, which is especially audible
+        smoothing: 0.05,
+        ..CONTINUOUS
+    },
+    Attack => ParamDescriptor {
+        id: "attack",
+        name: "attack",
+        default: 0.1,
+        ..CONTINUOUS
+    },
+    DecayDelay => ParamDescriptor {
+        id: "decay_delay",
+        name: "decay delay",
+        range: 0f32..=10f32,
+        default: 0.5,
+        unit: "s",
+        random: Randomize::Within(0f32..=3f32),
+        ..CONTINUOUS
+    },
+    Decay => ParamDescriptor {
+        id: "decay",
+        name: "decay",
+        default: 0.1,
+        ..CONTINUOUS
+    },
+    Sustain => ParamDescriptor {
+        id: "sustain",
+        name: "sustain",
+        default: 0.5,
+        ..CONTINUOUS
+    },
+    Release => ParamDescriptor {
+        id: "release",
+        name: "release",
+        default: 0.1,
+        ..CONTINUOUS
+    },
+    /// frequency of A4
+    MasterTune => ParamDescriptor {
+        id: "master_tune",
+        name: "master tune",
+        range: 400f32..=480f32,
+        default: tuning::REFERENCE_FREQ,
+        unit: "Hz",
+        format: format_one_decimal,
+        random: Randomize::Never,
+        ..CONTINUOUS
+    },
+    StereoMode => ParamDescriptor {
+        id: "stereo_mode",
+        name: "stereo mode",
+        range: 0f32..=3f32,
+        choices: &["mono", "arm / tip", "horizontal / vertical", "mid / side"],
+        ..CONTINUOUS
+    },
+    /// 0 is mono, 1 leaves the stereo mode's image as is, above 1 widens it
+    StereoWidth => ParamDescriptor {
+        id: "stereo_width",
+        name: "stereo width",
+        range: 0f32..=2f32,
+        default: 1.,
+        ..CONTINUOUS
+    },
+    // gain of each pendulum observable in the mono output mix
+    TapAngle1 => ParamDescriptor {
+        id: "tap_angle_1",
+        name: "angle 1",
+        ..TAP
+    },
+    TapAngle2 => ParamDescriptor {
+        id: "tap_angle_2",
+        name: "angle 2",
+        ..TAP
+    },
+    TapTipX => ParamDescriptor {
+        id: "tap_tip_x",
+        name: "tip x",
+        // just the horizontal tip position by default
+        default: 1.,
+        ..TAP
+    },
+    TapTipY => ParamDescriptor {
+        id: "tap_tip_y",
+        name: "tip y",
+        // mostly rumble at twice the pitch, better added on purpose
+        random: Randomize::Never,
+        ..TAP
+    },
+    TapAngularVelocity1 => ParamDescriptor {
+        id: "tap_angular_velocity_1",
+        name: "angular velocity 1",
+        ..TAP
+    },
+    TapAngularVelocity2 => ParamDescriptor {
+        id: "tap_angular_velocity_2",
+        name: "angular velocity 2",
+        ..TAP
+    },
+    TapMomentum1 => ParamDescriptor {
+        id: "tap_momentum_1",
+        name: "momentum 1",
+        ..TAP
+    },
+    TapMomentum2 => ParamDescriptor {
+        id: "tap_momentum_2",
+        name: "momentum 2",
+        ..TAP
+    },
+    TapKineticEnergy => ParamDescriptor {
+        id: "tap_kinetic_energy",
+        name: "kinetic energy",
+        random: Randomize::Never,
+        ..TAP
+    },
+    FilterMode => ParamDescriptor {
+        id: "filter_mode",
+        name: "filter mode",
+        range: 0f32..=3f32,
+        choices: &["lowpass", "bandpass", "highpass", "notch"],
+        ..FILTER
+    },
+    FilterCutoff => ParamDescriptor {
+        id: "filter_cutoff",
+        name: "cutoff",
+        range: 20f32..=20000f32,
+        default: 10000.,
+        unit: "Hz",
+        curve: Curve::Logarithmic,
+        format: format_integer,
+        random: Randomize::Within(200f32..=15000f32),
+        // the filter smooths its cutoff itself
+        smoothing: 0.,
+        ..FILTER
+    },
+    FilterResonance => ParamDescriptor {
+        id: "filter_resonance",
+        name: "resonance",
+        // roughly butterworth
+        default: 0.3,
+        // self oscillation is rarely wanted
+        random: Randomize::Within(0f32..=0.8f32),
+        ..FILTER
+    },
+    FilterEnvelopeAmount => ParamDescriptor {
+        id: "filter_envelope_amount",
+        name: "envelope amount",
+        range: -8f32..=8f32,
+        unit: "oct",
+        random: Randomize::Within(-4f32..=4f32),
+        ..FILTER
+    },
+    FilterAttack => ParamDescriptor {
+        id: "filter_attack",
+        name: "attack",
+        range: 0f32..=5f32,
+        default: 0.01,
+        unit: "s",
+        ..FILTER
+    },
+    FilterDecay => ParamDescriptor {
+        id: "filter_decay",
+        name: "decay",
+        range: 0f32..=5f32,
+        default: 0.3,
+        unit: "s",
+        ..FILTER
+    },
+    FilterSustain => ParamDescriptor {
+        id: "filter_sustain",
+        name: "sustain",
+        default: 0.5,
+        ..FILTER
+    },
+    FilterRelease => ParamDescriptor {
+        id: "filter_release",
+        name: "release",
+        range: 0f32..=5f32,
+        default: 0.3,
+        unit: "s",
+        ..FILTER
+    },
+    /// 1 makes the cutoff follow the note pitch
+    FilterKeyTracking => ParamDescriptor {
+        id: "filter_key_tracking",
+        name: "key tracking",
+        ..FILTER
+    },
+    /// cutoff modulation at full velocity
+    FilterVelocity => ParamDescriptor {
+        id: "filter_velocity",
+        name: "velocity",
+        range: -4f32..=4f32,
+        unit: "oct",
+        ..FILTER
+    },
+    Saturation => ParamDescriptor {
+        id: "saturation",
+        name: "saturation",
+        section: "saturation",
+        range: 0f32..=3f32,
+        choices: &["tanh", "cubic", "foldback", "hard clip"],
+        ..CONTINUOUS
+    },
+    /// the distortion amount, as gain before the saturation
+    Drive => ParamDescriptor {
+        id: "drive",
+        name: "drive",
+        section: "saturation",
+        range: 0f32..=36f32,
+        unit: "dB",
+        format: format_one_decimal,
+        random: Randomize::Within(0f32..=18f32),
+        ..CONTINUOUS
+    },
+    /// gain after the saturation
+    OutputGain => ParamDescriptor {
+        id: "output_gain",
+        name: "output gain",
+        section: "saturation",
+        range: -24f32..=12f32,
+        unit: "dB",
+        format: format_one_decimal,
+        random: Randomize::Never,
+        ..CONTINUOUS
+    },
+    Lfo1Shape => ParamDescriptor {
+        id: "lfo1_shape",
+        section: "lfo 1",
+        ..LFO_SHAPE
+    },
+    Lfo1Rate => ParamDescriptor {
+        id: "lfo1_rate",
+        section: "lfo 1",
+        ..LFO_RATE
+    },
+    Lfo1Sync => ParamDescriptor {
+        id: "lfo1_sync",
+        section: "lfo 1",
+        ..LFO_SYNC
+    },
+    Lfo1Phase => ParamDescriptor {
+        id: "lfo1_phase",
+        section: "lfo 1",
+        ..LFO_PHASE
+    },
+    Lfo1Mode => ParamDescriptor {
+        id: "lfo1_mode",
+        section: "lfo 1",
+        ..LFO_MODE
+    },
+    Lfo1Destination => ParamDescriptor {
+        id: "lfo1_destination",
+        section: "lfo 1",
+        ..LFO_DESTINATION
+    },
+    Lfo1Depth => ParamDescriptor {
+        id: "lfo1_depth",
+        section: "lfo 1",
+        ..LFO_DEPTH
+    },
+    Lfo2Shape => ParamDescriptor {
+        id: "lfo2_shape",
+        section: "lfo 2",
+        ..LFO_SHAPE
+    },
+    Lfo2Rate => ParamDescriptor {
+        id: "lfo2_rate",
+        section: "lfo 2",
+        ..LFO_RATE
+    },
+    Lfo2Sync => ParamDescriptor {
+        id: "lfo2_sync",
+        section: "lfo 2",
+        ..LFO_SYNC
+    },
+    Lfo2Phase => ParamDescriptor {
+        id: "lfo2_phase",
+        section: "lfo 2",
+        ..LFO_PHASE
+    },
+    Lfo2Mode => ParamDescriptor {
+        id: "lfo2_mode",
+        section: "lfo 2",
+        ..LFO_MODE
+    },
+    Lfo2Destination => ParamDescriptor {
+        id: "lfo2_destination",
+        section: "lfo 2",
+        ..LFO_DESTINATION
+    },
+    Lfo2Depth => ParamDescriptor {
+        id: "lfo2_depth",
+        section: "lfo 2",
+        ..LFO_DEPTH
+    },
+    Mod1Source => ParamDescriptor {
+        id: "mod1_source",
+        name: "mod 1 source",
+        // the mod wheel controls the chaoticity unless changed
+        default: 3.,
+        ..MOD_SOURCE
+    },
+    Mod1Destination => ParamDescriptor {
+        id: "mod1_destination",
+        name: "mod 1 destination",
+        default: 1.,
+        ..MOD_DESTINATION
+    },
+    Mod1Amount => ParamDescriptor {
+        id: "mod1_amount",
+        name: "mod 1 amount",
+        default: 1.,
+        ..MOD_AMOUNT
+    },
+    Mod1Curve => ParamDescriptor {
+        id: "mod1_curve",
+        name: "mod 1 curve",
+        ..MOD_CURVE
+    },
+    Mod2Source => ParamDescriptor {
+        id: "mod2_source",
+        name: "mod 2 source",
+        ..MOD_SOURCE
+    },
+    Mod2Destination => ParamDescriptor {
+        id: "mod2_destination",
+        name: "mod 2 destination",
+        ..MOD_DESTINATION
+    },
+    Mod2Amount => ParamDescriptor {
+        id: "mod2_amount",
+        name: "mod 2 amount",
+        ..MOD_AMOUNT
+    },
+    Mod2Curve => ParamDescriptor {
+        id: "mod2_curve",
+        name: "mod 2 curve",
+        ..MOD_CURVE
+    },
+    Mod3Source => ParamDescriptor {
+        id: "mod3_source",
+        name: "mod 3 source",
+        ..MOD_SOURCE
+    },
+    Mod3Destination => ParamDescriptor {
+        id: "mod3_destination",
+        name: "mod 3 destination",
+        ..MOD_DESTINATION
+    },
+    Mod3Amount => ParamDescriptor {
+        id: "mod3_amount",
+        name: "mod 3 amount",
+        ..MOD_AMOUNT
+    },
+    Mod3Curve => ParamDescriptor {
+        id: "mod3_curve",
+        name: "mod 3 curve",
+        ..MOD_CURVE
+    },
+    Mod4Source => ParamDescriptor {
+        id: "mod4_source",
+        name: "mod 4 source",
+        ..MOD_SOURCE
+    },
+    Mod4Destination => ParamDescriptor {
+        id: "mod4_destination",
+        name: "mod 4 destination",
+        ..MOD_DESTINATION
+    },
+    Mod4Amount => ParamDescriptor {
+        id: "mod4_amount",
+        name: "mod 4 amount",
+        ..MOD_AMOUNT
+    },
+    Mod4Curve => ParamDescriptor {
+        id: "mod4_curve",
+        name: "mod 4 curve",
+        ..MOD_CURVE
+    },
+    Mod5Source => ParamDescriptor {
+        id: "mod5_source",
+        name: "mod 5 source",
+        ..MOD_SOURCE
+    },
+    Mod5Destination => ParamDescriptor {
+        id: "mod5_destination",
+        name: "mod 5 destination",
+        ..MOD_DESTINATION
+    },
+    Mod5Amount => ParamDescriptor {
+        id: "mod5_amount",
+        name: "mod 5 amount",
+        ..MOD_AMOUNT
+    },
+    Mod5Curve => ParamDescriptor {
+        id: "mod5_curve",
+        name: "mod 5 curve",
+        ..MOD_CURVE
+    },
+    Mod6Source => ParamDescriptor {
+        id: "mod6_source",
+        name: "mod 6 source",
+        ..MOD_SOURCE
+    },
+    Mod6Destination => ParamDescriptor {
+        id: "mod6_destination",
+        name: "mod 6 destination",
+        ..MOD_DESTINATION
+    },
+    Mod6Amount => ParamDescriptor {
+        id: "mod6_amount",
+        name: "mod 6 amount",
+        ..MOD_AMOUNT
+    },
+    Mod6Curve => ParamDescriptor {
+        id: "mod6_curve",
+        name: "mod 6 curve",
+        ..MOD_CURVE
+    },
+    Mod7Source => ParamDescriptor {
+        id: "mod7_source",
+        name: "mod 7 source",
+        ..MOD_SOURCE
+    },
+    Mod7Destination => ParamDescriptor {
+        id: "mod7_destination",
+        name: "mod 7 destination",
+        ..MOD_DESTINATION
+    },
+    Mod7Amount => ParamDescriptor {
+        id: "mod7_amount",
+        name: "mod 7 amount",
+        ..MOD_AMOUNT
+    },
+    Mod7Curve => ParamDescriptor {
+        id: "mod7_curve",
+        name: "mod 7 curve",
+        ..MOD_CURVE
+    },
+    Mod8Source => ParamDescriptor {
+        id: "mod8_source",
+        name: "mod 8 source",
+        ..MOD_SOURCE
+    },
+    Mod8Destination => ParamDescriptor {
+        id: "mod8_destination",
+        name: "mod 8 destination",
+        ..MOD_DESTINATION
+    },
+    Mod8Amount => ParamDescriptor {
+        id: "mod8_amount",
+        name: "mod 8 amount",
+        ..MOD_AMOUNT
+    },
+    Mod8Curve => ParamDescriptor {
+        id: "mod8_curve",
+        name: "mod 8 curve",
+        ..MOD_CURVE
+    },
+    /// balance between the left and right channels
+    Pan => ParamDescriptor {
+        id: "pan",
+        name: "pan",
+        range: -1f32..=1f32,
+        random: Randomize::Never,
+        ..CONTINUOUS
+    },
+    /// speed of the chaos pendulum, a modulation source
+    ChaosRate => ParamDescriptor {
+        id: "chaos_rate",
+        name: "rate",
+        section: "chaos",
+        range: 0.01f32..=10f32,
+        default: 0.2,
+        unit: "Hz",
+        curve: Curve::Logarithmic,
+        ..CONTINUOUS
+    },
+    /// how wildly the chaos pendulum swings. low amounts are nearly periodic
+    ChaosAmount => ParamDescriptor {
+        id: "chaos_amount",
+        name: "amount",
+        section: "chaos",
+        default: 0.5,
+        ..CONTINUOUS
+    },
+    VelocityCurve => ParamDescriptor {
+        id: "velocity_curve",
+        name: "curve",
+        section: "velocity",
+        range: 0f32..=3f32,
+        choices: &["linear", "soft", "hard", "fixed"],
+        ..CONTINUOUS
+    },
+    /// how much of the note's energy depends on the velocity. 0 plays every note at full energy
+    VelocityEnergy => ParamDescriptor {
+        id: "velocity_energy",
+        name: "energy",
+        section: "velocity",
+        default: 1.,
+        ..CONTINUOUS
+    },
+    /// shortens the attack of hard notes, or lengthens it when negative
+    VelocityAttack => ParamDescriptor {
+        id: "velocity_attack",
+        name: "attack",
+        section: "velocity",
+        range: -1f32..=1f32,
+        ..CONTINUOUS
+    },
+    /// added to the chaoticity at full velocity
+    VelocityChaoticity => ParamDescriptor {
+        id: "velocity_chaoticity",
+        name: "chaoticity",
+        section: "velocity",
+        range: -0.9f32..=0.9f32,
+        ..CONTINUOUS
+    },
+    /// the note that key tracking is relative to
+    KeyTrackingCenter => ParamDescriptor {
+        id: "key_tracking_center",
+        name: "center key",
+        range: 0f32..=127f32,
+        default: 60.,
+        format: format_integer,
+        smoothing: 0.,
+        ..KEY_TRACKING
+    },
+    /// added to the chaoticity per octave above the center key
+    KeyTrackingChaoticity => ParamDescriptor {
+        id: "key_tracking_chaoticity",
+        name: "chaoticity",
+        unit: "/ oct",
+        range: -0.2f32..=0.2f32,
+        ..KEY_TRACKING
+    },
+    KeyTrackingAttack => ParamDescriptor {
+        id: "key_tracking_attack",
+        name: "attack",
+        ..KEY_TRACKING_TIME
+    },
+    KeyTrackingDecay => ParamDescriptor {
+        id: "key_tracking_decay",
+        name: "decay",
+        ..KEY_TRACKING_TIME
+    },
+    KeyTrackingRelease => ParamDescriptor {
+        id: "key_tracking_release",
+        name: "release",
+        ..KEY_TRACKING_TIME
+    },
+    /// evens out the level across notes and chaoticity. 0 leaves the level as it is
+    Normalization => ParamDescriptor {
+        id: "normalization",
+        name: "normalization",
+        section: "saturation",
+        random: Randomize::Never,
+        ..CONTINUOUS
+    },
+}
+
+const TAP: ParamDescriptor = ParamDescriptor {
+    section: "taps",
+    range: -1f32..=1f32,
+    // several taps at full gain easily drown out the rest
+    random: Randomize::Within(-0.5f32..=0.5f32),
+    ..CONTINUOUS
+};
+
+const FILTER: ParamDescriptor = ParamDescriptor {
+    section: "filter",
+    ..CONTINUOUS
+};
+
+const KEY_TRACKING: ParamDescriptor = ParamDescriptor {
+    section: "key tracking",
+    random: Randomize::Never,
+    ..CONTINUOUS
+};
+
+/// octaves the envelope time is shortened by per octave above the center key. 1 halves the time
+/// with every octave, negative values lengthen it
+const KEY_TRACKING_TIME: ParamDescriptor = ParamDescriptor {
+    range: -1f32..=1f32,
+    ..KEY_TRACKING
+};
+
+const LFO_SHAPE: ParamDescriptor = ParamDescriptor {
+    name: "shape",
+    range: 0f32..=5f32,
+    choices: &[
+        "sine",
+        "triangle",
+        "saw",
+        "square",
+        "sample & hold",
+        "smooth random",
+    ],
+    ..CONTINUOUS
+};
+
+const LFO_RATE: ParamDescriptor = ParamDescriptor {
+    name: "rate",
+    range: 0.01f32..=20f32,
+    default: 1.,
+    unit: "Hz",
+    curve: Curve::Logarithmic,
+    ..CONTINUOUS
+};
+
+/// overrides the rate with a note length at the host tempo
+const LFO_SYNC: ParamDescriptor = ParamDescriptor {
+    name: "sync",
+    range: 0f32..=8f32,
+    choices: &[
+        "free", "4 bars", "2 bars", "1 bar", "1/2", "1/4", "1/8", "1/16", "1/32",
+    ],
+    ..CONTINUOUS
+};
+
+const LFO_PHASE: ParamDescriptor = ParamDescriptor {
+    name: "phase",
+    range: 0f32..=360f32,
+    unit: "°",
+    format: format_integer,
+    ..CONTINUOUS
+};
+
+const LFO_MODE: ParamDescriptor = ParamDescriptor {
+    name: "mode",
+    range: 0f32..=1f32,
+    choices: &["global", "per voice"],
+    ..CONTINUOUS
+};
+
+const LFO_DESTINATION: ParamDescriptor = ParamDescriptor {
+    name: "destination",
+    range: 0f32..=3f32,
+    choices: &["chaoticity", "energy", "filter cutoff", "pitch"],
+    ..CONTINUOUS
+};
+
+const MOD_SOURCE: ParamDescriptor = ParamDescriptor {
+    section: MOD_SECTION,
+    range: 0f32..=14f32,
+    choices: &[
+        "none",
+        "velocity",
+        "key",
+        "mod wheel",
+        "aftertouch",
+        "pitch bend",
+        "lfo 1",
+        "lfo 2",
+        "filter envelope",
+        "arm angle",
+        "energy",
+        "chaos x",
+        "chaos y",
+        "tip angle",
+        "momentum",
+    ],
+    random: Randomize::Never,
+    ..CONTINUOUS
+};
+
+const MOD_DESTINATION: ParamDescriptor = ParamDescriptor {
+    section: MOD_SECTION,
+    range: 0f32..=NUM_MOD_DESTINATIONS as f32,
+    choices: &MOD_DESTINATION_CHOICES,
+    random: Randomize::Never,
+    ..CONTINUOUS
+};
+
+/// fraction of the destination's range at full source value
+const MOD_AMOUNT: ParamDescriptor = ParamDescriptor {
+    section: MOD_SECTION,
+    range: -1f32..=1f32,
+    random: Randomize::Never,
+    ..CONTINUOUS
+};
+
+const MOD_CURVE: ParamDescriptor = ParamDescriptor {
+    section: MOD_SECTION,
+    range: 0f32..=3f32,
+    choices: &["linear", "exponential", "logarithmic", "s-curve"],
+    random: Randomize::Never,
+    ..CONTINUOUS
+};
+
+/// negative inverts the lfo
+const LFO_DEPTH: ParamDescriptor = ParamDescriptor {
+    name: "depth",
+    range: -1f32..=1f32,
+    random: Randomize::Within(-0.3f32..=0.3f32),
+    ..CONTINUOUS
+};
+
+impl Param {
+    pub fn descriptor(self) -> &'static ParamDescriptor {
+        &DESCRIPTORS[self as usize]
+    }
+
+    pub fn from_id(id: &str) -> Option<Param> {
+        Param::ALL.into_iter().find(|p| p.descriptor().id == id)
+    }
+
+    pub fn tap_gain(tap: Tap) -> Param {
+        match tap {
+            Tap::Angle1 => Param::TapAngle1,
+            Tap::Angle2 => Param::TapAngle2,
+            Tap::TipX => Param::TapTipX,
+            Tap::TipY => Param::TapTipY,
+            Tap::AngularVelocity1 => Param::TapAngularVelocity1,
+            Tap::AngularVelocity2 => Param::TapAngularVelocity2,
+            Tap::Momentum1 => Param::TapMomentum1,
+            Tap::Momentum2 => Param::TapMomentum2,
+            Tap::KineticEnergy => Param::TapKineticEnergy,
+        }
+    }
+}
+
+/// the current parameter values, readable from any thread.
+/// edits are sent to the synth as `Message::SetParam`, to be applied in order with the midi.
+/// whole sounds are loaded with `State::apply`, which writes here directly so the result can be
+/// read back right away
+pub struct Params {
+    /// indexed by `Param`
+    values: [AtomicCell<f32>; NUM_PARAMS],
+    tuning: Mutex<Tuning>,
+    // kept separately from the tuning to not have to lock in the audio thread
+    note_frequencies: AtomicCell<[f32; tuning::NUM_NOTES]>,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            values: Param::ALL.map(|param| param.descriptor().default.into()),
+            tuning: Mutex::new(Tuning::default()),
+            note_frequencies: Tuning::default().frequencies().into(),
+        }
+    }
+}
+
+impl Params {
+    /// clamped to the parameter's range. choice parameters are returned as the index of the choice
+    pub fn get(&self, param: Param) -> f32 {
+        param.descriptor().clamp(self.values[param as usize].load())
+    }
+
+    pub(crate) fn set(&self, param: Param, value: f32) {
+        self.values[param as usize].store(value);
+    }
+
+    pub fn get_tuning(&self) -> Tuning {
+        self.tuning.lock().unwrap().clone()
+    }
+
+    pub fn set_tuning(&self, tuning: Tuning) {
+        self.note_frequencies.store(tuning.frequencies());
+        *self.tuning.lock().unwrap() = tuning;
+    }
+
+    /// play notes at frequencies precomputed from a tuning, without locking or allocating.
+    /// `get_tuning` still returns the old one until a `set_tuning`
+    pub(crate) fn set_note_frequencies(&self, frequencies: [f32; tuning::NUM_NOTES]) {
+        self.note_frequencies.store(frequencies);
+    }
+
+    /// frequency of a note using the current tuning, or 0 if the note is unmapped
+    pub(crate) fn get_note_freq(&self, note: wmidi::Note) -> f32 {
+        self.note_frequencies.load()[u8::from(note) as usize] * self.get(Param::MasterTune)
+            / tuning::REFERENCE_FREQ
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Param, Randomize, NUM_PARAMS};
+    use crate::{
+        lfo::LFO_PARAMS,
+        mod_matrix::{MOD_DESTINATIONS, MOD_SLOT_PARAMS},
+        FilterMode, LfoDestination, LfoMode, LfoShape, LfoSync, ModCurve, ModSource, Saturation,
+        StereoMode, VelocityCurve,
+    };
+    use std::collections::HashSet;
+
+    /// the position hosts have saved automation under
+    #[test]
+    fn automation_indices() {
+        let ids: &[&str] = &[
+            "chaoticity",
+            "attack",
+            "decay_delay",
+            "decay",
+            "sustain",
+            "release",
+            "master_tune",
+            "stereo_mode",
+            "stereo_width",
+            "tap_angle_1",
+            "tap_angle_2",
+            "tap_tip_x",
+            "tap_tip_y",
+            "tap_angular_velocity_1",
+            "tap_angular_velocity_2",
+            "tap_momentum_1",
+            "tap_momentum_2",
+            "tap_kinetic_energy",
+            "filter_mode",
+            "filter_cutoff",
+            "filter_resonance",
+            "filter_envelope_amount",
+            "filter_attack",
+            "filter_decay",
+            "filter_sustain",
+            "filter_release",
+            "filter_key_tracking",
+            "filter_velocity",
+            "saturation",
+            "drive",
+            "output_gain",
+            "lfo1_shape",
+            "lfo1_rate",
+            "lfo1_sync",
+            "lfo1_phase",
+            "lfo1_mode",
+            "lfo1_destination",
+            "lfo1_depth",
+            "lfo2_shape",
+            "lfo2_rate",
+            "lfo2_sync",
+            "lfo2_phase",
+            "lfo2_mode",
+            "lfo2_destination",
+            "lfo2_depth",
+            "mod1_source",
+            "mod1_destination",
+            "mod1_amount",
+            "mod1_curve",
+            "mod2_source",
+            "mod2_destination",
+            "mod2_amount",
+            "mod2_curve",
+            "mod3_source",
+            "mod3_destination",
+            "mod3_amount",
+            "mod3_curve",
+            "mod4_source",
+            "mod4_destination",
+            "mod4_amount",
+            "mod4_curve",
+            "mod5_source",
+            "mod5_destination",
+            "mod5_amount",
+            "mod5_curve",
+            "mod6_source",
+            "mod6_destination",
+            "mod6_amount",
+            "mod6_curve",
+            "mod7_source",
+            "mod7_destination",
+            "mod7_amount",
+            "mod7_curve",
+            "mod8_source",
+            "mod8_destination",
+            "mod8_amount",
+            "mod8_curve",
+            "pan",
+            "chaos_rate",
+            "chaos_amount",
+            "velocity_curve",
+            "velocity_energy",
+            "velocity_attack",
+            "velocity_chaoticity",
+            "key_tracking_center",
+            "key_tracking_chaoticity",
+            "key_tracking_attack",
+            "key_tracking_decay",
+            "key_tracking_release",
+            "normalization",
+        ];
+        for (index, id) in ids.iter().enumerate() {
+            assert_eq!(Param::ALL[index].descriptor().id, *id, "{index}");
+        }
+    }
+
+    #[test]
+    fn descriptors_consistent() {
+        let ids: HashSet<_> = Param::ALL.iter().map(|p| p.descriptor().id).collect();
+        assert_eq!(ids.len(), NUM_PARAMS);
+        for param in Param::ALL {
+            let d = param.descriptor();
+            assert_eq!(Param::from_id(d.id), Some(param));
+            assert!(d.range.contains(&d.default), "{}", d.id);
+            if d.is_choice() {
+                assert_eq!(*d.range.end() as usize + 1, d.choices.len(), "{}", d.id);
+            }
+            if let Randomize::Within(random) = &d.random {
+                assert!(
+                    d.range.contains(random.start()) && d.range.contains(random.end()),
+                    "{}",
+                    d.id
+                );
+            }
+            assert!(d.smoothing >= 0., "{}", d.id);
+            for n in [0., 0.3, 1.] {
+                let value = d.from_normalized(n);
+                assert!(
+                    (d.from_normalized(d.to_normalized(value)) - value).abs()
+                        <= 1e-4 * value.abs().max(1.)
+                );
+            }
+        }
+        assert_eq!(
+            Param::StereoMode.descriptor().choices.len(),
+            StereoMode::ALL.len()
+        );
+        assert_eq!(
+            Param::FilterMode.descriptor().choices.len(),
+            FilterMode::ALL.len()
+        );
+        for slot in &MOD_SLOT_PARAMS {
+            assert_eq!(slot.source.descriptor().choices.len(), ModSource::ALL.len());
+            assert_eq!(slot.curve.descriptor().choices.len(), ModCurve::ALL.len());
+            assert_eq!(
+                slot.destination.descriptor().choices.len(),
+                MOD_DESTINATIONS.len() + 1
+            );
+        }
+        for lfo in &LFO_PARAMS {
+            assert_eq!(lfo.shape.descriptor().choices.len(), LfoShape::ALL.len());
+            assert_eq!(lfo.sync.descriptor().choices.len(), LfoSync::ALL.len());
+            assert_eq!(lfo.mode.descriptor().choices.len(), LfoMode::ALL.len());
+            assert_eq!(
+                lfo.destination.descriptor().choices.len(),
+                LfoDestination::ALL.len()
+            );
+        }
+        assert_eq!(
+            Param::Saturation.descriptor().choices.len(),
+            Saturation::ALL.len()
+        );
+        assert_eq!(
+            Param::VelocityCurve.descriptor().choices.len(),
+            VelocityCurve::ALL.len()
+        );
+    }
+}
@@ -1,27 +1,137 @@
-use std::ops::RangeInclusive;
-
-use crossbeam::atomic::AtomicCell;
 use egui::Ui;
+use log::warn;
 
 use crate::{
-    Params, ATTACK_RANGE, CHAOTICITY_RANGE, DECAY_DELAY_RANGE, DECAY_RANGE, RELEASE_RANGE,
-    SUSTAIN_RANGE,
+    mod_matrix::{MOD_SECTION, MOD_SLOT_PARAMS},
+    Curve, History, Message, MessageSender, Param, Params, Randomizer,
 };
 
-fn param(ui: &mut Ui, param: &AtomicCell<f32>, name: &str, range: RangeInclusive<f32>) {
-    ui.label(name);
-    let mut p = param.load();
-    ui.add(egui::Slider::new(&mut p, range));
-    param.store(p);
+struct ParamsUi<'a> {
+    params: &'a Params,
+    sender: &'a MessageSender,
+    history: &'a mut History,
+    randomizer: &'a mut Randomizer,
+}
+
+impl ParamsUi<'_> {
+    fn send(&mut self, param: Param, before: f32, value: f32) {
+        if let Err(e) = self.sender.try_send(Message::SetParam(param, value)) {
+            warn!("error sending param change {}", e);
+        }
+        self.history.record(param, before, value);
+    }
+
+    fn param(&mut self, ui: &mut Ui, param: Param) {
+        let descriptor = param.descriptor();
+        ui.horizontal(|ui| {
+            ui.label(format!("{}:", descriptor.name));
+            let mut locked = self.randomizer.is_locked(param);
+            if ui
+                .checkbox(&mut locked, "lock")
+                .on_hover_text("keep when randomizing")
+                .changed()
+            {
+                self.randomizer.set_locked(param, locked);
+            }
+        });
+        self.widget(ui, param);
+    }
+
+    /// the control without a label
+    fn widget(&mut self, ui: &mut Ui, param: Param) {
+        let descriptor = param.descriptor();
+        let current = self.params.get(param);
+        if descriptor.is_choice() {
+            let current = current as usize;
+            let mut selected = current;
+            egui::ComboBox::from_id_source(descriptor.id)
+                .selected_text(descriptor.choices[current])
+                .show_ui(ui, |ui| {
+                    for (i, &name) in descriptor.choices.iter().enumerate() {
+                        ui.selectable_value(&mut selected, i, name);
+                    }
+                });
+            if selected != current {
+                self.send(param, current as f32, selected as f32);
+            }
+        } else {
+            let mut value = current;
+            let mut slider = egui::Slider::new(&mut value, descriptor.range.clone())
+                .logarithmic(descriptor.curve == Curve::Logarithmic);
+            if !descriptor.unit.is_empty() {
+                slider = slider.suffix(format!(" {}", descriptor.unit));
+            }
+            let response = ui.add(slider);
+            // a whole drag is undone in one step
+            if response.drag_started() {
+                self.history.begin();
+            }
+            if response.changed() {
+                self.send(param, current, value);
+            }
+            if response.drag_released() {
+                self.history.end();
+            }
+        }
+    }
+
+    /// one row per slot
+    fn mod_matrix(&mut self, ui: &mut Ui) {
+        egui::Grid::new("mod matrix").show(ui, |ui| {
+            for heading in ["", "source", "destination", "curve", "amount"] {
+                ui.label(heading);
+            }
+            ui.end_row();
+            for (i, slot) in MOD_SLOT_PARAMS.iter().enumerate() {
+                ui.label(format!("{}", i + 1));
+                for param in [slot.source, slot.destination, slot.curve, slot.amount] {
+                    self.widget(ui, param);
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    fn section(&mut self, ui: &mut Ui, section: &str) {
+        for param in Param::ALL {
+            if param.descriptor().section == section {
+                self.param(ui, param);
+            }
+        }
+    }
 }
 
-pub fn params_gui(ui: &mut Ui, params: &Params) {
+/// edits are recorded in `history`
+pub fn params_gui(
+    ui: &mut Ui,
+    params: &Params,
+    sender: &MessageSender,
+    history: &mut History,
+    randomizer: &mut Randomizer,
+) {
+    let mut p = ParamsUi {
+        params,
+        sender,
+        history,
+        randomizer,
+    };
+    // sections in the order they first appear in
+    let mut sections: Vec<&str> = vec![];
+    for param in Param::ALL {
+        let section = param.descriptor().section;
+        if !sections.contains(&section) {
+            sections.push(section);
+        }
+    }
     ui.vertical(|ui| {
-        param(ui, &params.chaoticity, "chaoticity:", CHAOTICITY_RANGE);
-        param(ui, &params.attack, "attack:", ATTACK_RANGE);
-        param(ui, &params.decay, "decay:", DECAY_RANGE);
-        param(ui, &params.decay_delay, "decay_delay:", DECAY_DELAY_RANGE);
-        param(ui, &params.sustain, "sustain:", SUSTAIN_RANGE);
-        param(ui, &params.release, "release:", RELEASE_RANGE);
+        for section in sections {
+            if section.is_empty() {
+                p.section(ui, section);
+            } else if section == MOD_SECTION {
+                ui.collapsing(section, |ui| p.mod_matrix(ui));
+            } else {
+                ui.collapsing(section, |ui| p.section(ui, section));
+            }
+        }
     });
 }
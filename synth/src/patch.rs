@@ -0,0 +1,229 @@
+// patch files. a stored sound together with some metadata, in toml
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fmt};
+
+use crate::{tuning::ParseError, KeyboardMapping, Param, Params, Scale, State, Tuning};
+
+/// bump when the meaning of existing fields changes
+pub const PATCH_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum PatchError {
+    Toml(toml::de::Error),
+    Tuning(ParseError),
+    Utf8(std::str::Utf8Error),
+    Bank(String),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Toml(e) => write!(f, "invalid patch: {e}"),
+            PatchError::Tuning(e) => write!(f, "invalid tuning: {e}"),
+            PatchError::Utf8(e) => write!(f, "invalid text: {e}"),
+            PatchError::Bank(e) => write!(f, "invalid bank: {e}"),
+        }
+    }
+}
+
+impl Error for PatchError {}
+
+/// missing fields get their defaults, unknown ones are ignored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Patch {
+    pub version: u32,
+    pub name: String,
+    pub author: String,
+    pub tags: Vec<String>,
+    /// source of the scala scale, if not 12 tone equal temperament
+    pub scale: Option<String>,
+    /// source of the scala keyboard mapping, if not the default linear one
+    pub keyboard_mapping: Option<String>,
+    /// values by parameter id. choice parameters are stored as their index
+    pub params: BTreeMap<String, f32>,
+}
+
+impl Default for Patch {
+    fn default() -> Self {
+        Self {
+            version: PATCH_VERSION,
+            name: String::new(),
+            author: String::new(),
+            tags: vec![],
+            scale: None,
+            keyboard_mapping: None,
+            params: BTreeMap::new(),
+        }
+    }
+}
+
+impl Patch {
+    pub fn from_toml(source: &str) -> Result<Self, PatchError> {
+        let patch: Self = toml::from_str(source).map_err(PatchError::Toml)?;
+        if patch.version > PATCH_VERSION {
+            warn!("loading patch from a newer version {}", patch.version);
+        }
+        Ok(patch)
+    }
+
+    pub fn to_toml(&self) -> String {
+        // only fails for types toml can't represent, which a patch doesn't contain
+        toml::to_string(self).unwrap()
+    }
+
+    /// the sound without the metadata. values are clamped to their ranges
+    pub fn to_state(&self) -> Result<State, PatchError> {
+        let mut state = State::default();
+        for (id, &value) in &self.params {
+            match Param::from_id(id) {
+                Some(param) => state.values[param as usize] = param.descriptor().clamp(value),
+                None => warn!("ignoring unknown patch parameter {id}"),
+            }
+        }
+        state.tuning = Tuning {
+            scale: self
+                .scale
+                .as_deref()
+                .map(Scale::parse)
+                .transpose()
+                .map_err(PatchError::Tuning)?,
+            keyboard_mapping: self
+                .keyboard_mapping
+                .as_deref()
+                .map(KeyboardMapping::parse)
+                .transpose()
+                .map_err(PatchError::Tuning)?,
+        };
+        Ok(state)
+    }
+
+    /// a patch without metadata
+    pub fn from_state(state: &State) -> Self {
+        Self {
+            scale: state.tuning.scale.as_ref().map(|s| s.source().to_string()),
+            keyboard_mapping: state
+                .tuning
+                .keyboard_mapping
+                .as_ref()
+                .map(|m| m.source().to_string()),
+            params: Param::ALL
+                .iter()
+                .map(|&param| {
+                    (
+                        param.descriptor().id.to_string(),
+                        state.values[param as usize],
+                    )
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+}
+
+impl Params {
+    /// the current sound, without metadata
+    pub fn to_patch(&self) -> Patch {
+        Patch::from_state(&State::capture(self))
+    }
+
+    /// nothing is changed if the patch is invalid
+    pub fn from_patch(&self, patch: &Patch) -> Result<(), PatchError> {
+        patch.to_state()?.apply(self);
+        Ok(())
+    }
+
+    /// the current sound as plugin state, which is its patch toml
+    pub fn to_chunk(&self) -> Vec<u8> {
+        self.to_patch().to_toml().into_bytes()
+    }
+
+    /// plugin state from `to_chunk`, of this or any other version.
+    /// nothing is changed if it is invalid
+    pub fn from_chunk(&self, chunk: &[u8]) -> Result<(), PatchError> {
+        let source = std::str::from_utf8(chunk).map_err(PatchError::Utf8)?;
+        self.from_patch(&Patch::from_toml(source)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Patch;
+    use crate::{KeyboardMapping, Param, Params, Scale, State, Tuning};
+
+    #[test]
+    fn round_trip() {
+        let mut state = State::default();
+        state.values[Param::FilterCutoff as usize] = 440.;
+        state.values[Param::StereoMode as usize] = 3.;
+        let patch = Patch {
+            name: "test".to_string(),
+            tags: vec!["bell".to_string()],
+            scale: Some("! 5edo\n5 equal\n5\n240.\n480.\n720.\n960.\n2/1\n".to_string()),
+            ..Patch::from_state(&state)
+        };
+        let loaded = Patch::from_toml(&patch.to_toml()).unwrap();
+        assert_eq!(loaded, patch);
+        let loaded_state = loaded.to_state().unwrap();
+        assert_eq!(loaded_state.values, state.values);
+        assert_eq!(loaded_state.tuning.scale.unwrap().ratios.len(), 5);
+    }
+
+    #[test]
+    fn chunk_round_trip() {
+        let params = Params::default();
+        params.set(Param::FilterCutoff, 440.);
+        params.set(Param::StereoMode, 3.);
+        params.set_tuning(Tuning {
+            scale: Some(Scale::parse("just\n2\n3/2\n2/1\n").unwrap()),
+            keyboard_mapping: Some(
+                KeyboardMapping::parse(
+                    "! two keys per octave\n2\n0\n127\n60\n60\n100.0\n0\n0\nx\n",
+                )
+                .unwrap(),
+            ),
+        });
+        let loaded = Params::default();
+        loaded.from_chunk(&params.to_chunk()).unwrap();
+        assert_eq!(State::capture(&loaded), State::capture(&params));
+        assert!(loaded.from_chunk(&[0xff, 0xfe]).is_err());
+        assert!(loaded.from_chunk(b"params = 1").is_err());
+        assert_eq!(State::capture(&loaded), State::capture(&params));
+    }
+
+    /// what a host may have saved with an older version, or one with parameters that are gone
+    #[test]
+    fn old_chunk() {
+        let params = Params::default();
+        params.set(Param::Release, 0.9);
+        params
+            .from_chunk(
+                b"version = 0\nname = \"old\"\nunknown = true\n[params]\nchaoticity = 0.25\nremoved = 1.0\n",
+            )
+            .unwrap();
+        let mut expected = State::default();
+        expected.values[Param::Chaoticity as usize] = 0.25;
+        assert_eq!(State::capture(&params), expected);
+    }
+
+    #[test]
+    fn defaults_and_clamping() {
+        let patch = Patch::from_toml(
+            "version = 99\nname = \"old\"\n[params]\nchaoticity = 7.0\nremoved = 1.0\n",
+        )
+        .unwrap();
+        assert_eq!(patch.name, "old");
+        let state = patch.to_state().unwrap();
+        assert_eq!(state.values[Param::Chaoticity as usize], 1.);
+        assert_eq!(
+            state.values[Param::Release as usize],
+            Param::Release.descriptor().default
+        );
+        assert!(Patch::from_toml("scale = \"bad\"")
+            .unwrap()
+            .to_state()
+            .is_err());
+    }
+}
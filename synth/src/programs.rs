@@ -0,0 +1,352 @@
+// a bank of sounds that hosts and midi program changes switch between.
+// every program keeps its own state, so edits survive switching away and back
+
+use crossbeam::atomic::AtomicCell;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::{tuning::NUM_NOTES, Param, Params, Patch, PatchError, State, Tuning};
+
+/// bump when the meaning of existing bank fields changes
+const BANK_VERSION: u32 = 1;
+
+struct Program {
+    name: String,
+    state: State,
+    /// of the state's tuning, for switching to it without allocating
+    frequencies: [f32; NUM_NOTES],
+}
+
+impl Program {
+    fn new(name: String, state: State) -> Self {
+        Self {
+            name,
+            frequencies: state.tuning.frequencies(),
+            state,
+        }
+    }
+
+    fn set_tuning(&mut self, tuning: Tuning) {
+        self.frequencies = tuning.frequencies();
+        self.state.tuning = tuning;
+    }
+}
+
+/// the serialized form of a whole bank
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Bank {
+    /// 0 if missing, which makes it something other than a bank
+    version: u32,
+    current: usize,
+    programs: Vec<Patch>,
+}
+
+/// `Programs::tuning_owner` when the tuning in the params is the current program's
+const NO_PROGRAM: usize = usize::MAX;
+
+pub struct Programs {
+    programs: Mutex<Vec<Program>>,
+    current: AtomicCell<usize>,
+    /// the program switched away from by `try_select`, whose tuning is still in the params
+    /// until `update`
+    tuning_owner: AtomicCell<usize>,
+}
+
+fn to_programs(patches: &[Patch]) -> Vec<Program> {
+    patches
+        .iter()
+        .filter_map(|patch| match patch.to_state() {
+            Ok(state) => Some(Program::new(patch.name.clone(), state)),
+            Err(e) => {
+                warn!("skipping program {}: {e}", patch.name);
+                None
+            }
+        })
+        .collect()
+}
+
+impl Programs {
+    /// invalid patches are left out
+    pub fn new(patches: &[Patch]) -> Self {
+        Self {
+            programs: Mutex::new(to_programs(patches)),
+            current: AtomicCell::new(0),
+            tuning_owner: AtomicCell::new(NO_PROGRAM),
+        }
+    }
+
+    /// add programs after the existing ones. invalid patches are left out
+    pub fn extend(&self, patches: &[Patch]) {
+        self.programs.lock().unwrap().extend(to_programs(patches));
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn current(&self) -> usize {
+        self.current.load()
+    }
+
+    pub fn name(&self, index: usize) -> Option<String> {
+        Some(self.programs.lock().unwrap().get(index)?.name.clone())
+    }
+
+    pub fn set_name(&self, index: usize, name: String) {
+        if let Some(program) = self.programs.lock().unwrap().get_mut(index) {
+            program.name = name;
+        }
+    }
+
+    /// the part of `update` that needs the programs locked
+    fn update_locked(&self, programs: &mut [Program], params: &Params) {
+        let owner = self.tuning_owner.swap(NO_PROGRAM);
+        if owner == NO_PROGRAM {
+            return;
+        }
+        if let Some(program) = programs.get_mut(owner) {
+            program.set_tuning(params.get_tuning());
+        }
+        if let Some(program) = programs.get(self.current()) {
+            params.set_tuning(program.state.tuning.clone());
+        }
+    }
+
+    /// finish a program change made by the audio thread, by moving the tuning along with it.
+    /// that allocates, so call this regularly from a thread that isn't realtime, like the gui's
+    pub fn update(&self, params: &Params) {
+        self.update_locked(&mut self.programs.lock().unwrap(), params);
+    }
+
+    fn store_current_locked(&self, programs: &mut [Program], params: &Params) {
+        self.update_locked(programs, params);
+        if let Some(program) = programs.get_mut(self.current()) {
+            let State { values, tuning } = State::capture(params);
+            program.state.values = values;
+            program.set_tuning(tuning);
+        }
+    }
+
+    /// remember the current sound as the state of the current program
+    pub fn store_current(&self, params: &Params) {
+        self.store_current_locked(&mut self.programs.lock().unwrap(), params);
+    }
+
+    /// store the current sound, then switch to the program and apply its state.
+    /// does nothing if there is no such program
+    pub fn select(&self, index: usize, params: &Params) {
+        let mut programs = self.programs.lock().unwrap();
+        if index >= programs.len() {
+            return;
+        }
+        self.store_current_locked(&mut programs, params);
+        self.current.store(index);
+        programs[index].state.apply(params);
+    }
+
+    /// `select` for the audio thread, which can't block or allocate. the values are swapped in
+    /// place and notes play in the new tuning right away, while the tuning itself is moved by
+    /// the next `update`. false if another thread has the programs locked, to try again later
+    pub(crate) fn try_select(&self, index: usize, params: &Params) -> bool {
+        let mut programs = match self.programs.try_lock() {
+            Ok(programs) => programs,
+            Err(_) => return false,
+        };
+        // out of range program changes are ignored
+        if index >= programs.len() {
+            return true;
+        }
+        let current = self.current();
+        if let Some(program) = programs.get_mut(current) {
+            for param in Param::ALL {
+                program.state.values[param as usize] = params.get(param);
+            }
+        }
+        // if switching again before an update, the tuning is still the first program's
+        let _ = self.tuning_owner.compare_exchange(NO_PROGRAM, current);
+        let program = &programs[index];
+        for param in Param::ALL {
+            params.set(param, program.state.values[param as usize]);
+        }
+        params.set_note_frequencies(program.frequencies);
+        self.current.store(index);
+        true
+    }
+
+    /// all programs, with the current sound stored in the current one
+    pub fn to_toml(&self, params: &Params) -> String {
+        self.store_current(params);
+        let bank = Bank {
+            version: BANK_VERSION,
+            current: self.current(),
+            programs: self
+                .programs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|program| Patch {
+                    name: program.name.clone(),
+                    ..Patch::from_state(&program.state)
+                })
+                .collect(),
+        };
+        // only fails for types toml can't represent, which a bank doesn't contain
+        toml::to_string(&bank).unwrap()
+    }
+
+    /// replace all programs, returning the state of the new current one.
+    /// nothing is changed if the bank has no valid programs or is of another version
+    pub fn load_toml(&self, source: &str) -> Result<State, PatchError> {
+        let bank: Bank = toml::from_str(source).map_err(PatchError::Toml)?;
+        if bank.version != BANK_VERSION {
+            return Err(PatchError::Bank(format!(
+                "unsupported version {}",
+                bank.version
+            )));
+        }
+        let programs = to_programs(&bank.programs);
+        if programs.is_empty() {
+            return Err(PatchError::Bank("no programs".to_string()));
+        }
+        let current = bank.current.min(programs.len() - 1);
+        let state = programs[current].state.clone();
+        let mut locked = self.programs.lock().unwrap();
+        *locked = programs;
+        // a tuning waiting to be moved belongs to the replaced programs
+        self.tuning_owner.store(NO_PROGRAM);
+        self.current.store(current);
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Message, Param, Params, Patch, Synth, SynthPlayer, Tuning};
+    use crossbeam::channel;
+
+    fn program_change(program: u8) -> Message {
+        wmidi::MidiMessage::ProgramChange(wmidi::Channel::Ch1, wmidi::U7::from_u8_lossy(program))
+            .into()
+    }
+
+    #[test]
+    fn program_change_keeps_edits() {
+        let (tx, rx) = channel::bounded(8);
+        let mut synth = Synth::new(rx);
+        let (params, programs) = (synth.get_params(), synth.get_programs());
+        assert!(programs.len() > 1);
+        let mut data = [0f32; 64];
+        params.set(Param::Drive, 20.);
+        tx.send(program_change(1)).unwrap();
+        synth.play(48000, 2, &mut data);
+        assert_eq!(programs.current(), 1);
+        assert_ne!(params.get(Param::Drive), 20.);
+        tx.send(program_change(0)).unwrap();
+        synth.play(48000, 2, &mut data);
+        assert_eq!(params.get(Param::Drive), 20.);
+        // out of range programs are ignored
+        tx.send(program_change(127)).unwrap();
+        synth.play(48000, 2, &mut data);
+        assert_eq!(programs.current(), 0);
+    }
+
+    #[test]
+    fn program_change_waits_for_lock() {
+        let (tx, rx) = channel::bounded(8);
+        let mut synth = Synth::new(rx);
+        let programs = synth.get_programs();
+        let mut data = [0f32; 64];
+        let locked = programs.programs.lock().unwrap();
+        tx.send(program_change(1)).unwrap();
+        synth.play(48000, 2, &mut data);
+        assert_eq!(programs.current(), 0);
+        drop(locked);
+        synth.play(48000, 2, &mut data);
+        assert_eq!(programs.current(), 1);
+    }
+
+    #[test]
+    fn program_change_moves_tuning() {
+        let (tx, rx) = channel::bounded(8);
+        let mut synth = Synth::new(rx);
+        let (params, programs) = (synth.get_params(), synth.get_programs());
+        let patch = Patch {
+            name: "5edo".to_string(),
+            scale: Some("5 equal\n5\n240.\n480.\n720.\n960.\n2/1\n".to_string()),
+            ..Patch::default()
+        };
+        let reference = Params::default();
+        reference.set_tuning(patch.to_state().unwrap().tuning);
+        programs.extend(&[patch]);
+        let index = programs.len() - 1;
+        let mut data = [0f32; 64];
+        tx.send(program_change(index as u8)).unwrap();
+        synth.play(48000, 2, &mut data);
+        assert_eq!(programs.current(), index);
+        // notes play in the new tuning right away, the tuning itself follows with the update
+        let note = wmidi::Note::C5;
+        assert_eq!(params.get_note_freq(note), reference.get_note_freq(note));
+        assert_eq!(params.get_tuning(), Tuning::default());
+        programs.update(&params);
+        assert_eq!(params.get_tuning(), reference.get_tuning());
+        tx.send(program_change(0)).unwrap();
+        synth.play(48000, 2, &mut data);
+        programs.update(&params);
+        assert_eq!(params.get_tuning(), Tuning::default());
+        assert_eq!(
+            params.get_note_freq(note),
+            Params::default().get_note_freq(note)
+        );
+    }
+
+    #[test]
+    fn select_is_immediate() {
+        let (_tx, rx) = channel::bounded(1);
+        let synth = Synth::new(rx);
+        let (params, programs) = (synth.get_params(), synth.get_programs());
+        params.set(Param::Drive, 20.);
+        programs.select(1, &params);
+        assert_eq!(programs.current(), 1);
+        assert_ne!(params.get(Param::Drive), 20.);
+        programs.select(0, &params);
+        assert_eq!(params.get(Param::Drive), 20.);
+    }
+
+    #[test]
+    fn bank_round_trip() {
+        let (_tx, rx) = channel::bounded(1);
+        let synth = Synth::new(rx);
+        let programs = synth.get_programs();
+        programs.set_name(1, "renamed".to_string());
+        let bank = programs.to_toml(&synth.get_params());
+        let state = programs
+            .load_toml(&bank.replace("renamed", "loaded"))
+            .unwrap();
+        assert_eq!(programs.name(1).as_deref(), Some("loaded"));
+        assert_eq!(state.values, Patch::default().to_state().unwrap().values);
+    }
+
+    #[test]
+    fn invalid_banks() {
+        let (_tx, rx) = channel::bounded(1);
+        let synth = Synth::new(rx);
+        let programs = synth.get_programs();
+        let len = programs.len();
+        let bank = programs.to_toml(&synth.get_params());
+        // any toml parses as a bank, since every field has a default
+        assert!(programs.load_toml("").is_err());
+        assert!(programs.load_toml("[tuning]\nname = \"x\"").is_err());
+        assert!(programs.load_toml("version = 1\nprograms = []").is_err());
+        assert!(programs
+            .load_toml(&bank.replacen("version = 1", "version = 2", 1))
+            .is_err());
+        assert_eq!(programs.len(), len);
+        assert!(programs.load_toml(&bank).is_ok());
+    }
+}
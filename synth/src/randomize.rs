@@ -0,0 +1,206 @@
+// new sounds from random parameter values, within the ranges the descriptors consider safe
+
+use egui::Ui;
+use log::warn;
+
+use crate::{
+    History, Message, MessageSender, Param, ParamDescriptor, Params, Randomize, NUM_PARAMS,
+};
+
+/// normalized bounds randomizing picks values within, or None if the parameter is never randomized
+fn random_bounds(descriptor: &ParamDescriptor) -> Option<(f32, f32)> {
+    match &descriptor.random {
+        Randomize::Never => None,
+        Randomize::Full => Some((0., 1.)),
+        Randomize::Within(range) => Some((
+            descriptor.to_normalized(*range.start()),
+            descriptor.to_normalized(*range.end()),
+        )),
+    }
+}
+
+pub struct Randomizer {
+    /// indexed by `Param`. locked parameters are never changed
+    locked: [bool; NUM_PARAMS],
+    /// how far mutating moves the parameters, as a fraction of their range
+    pub amount: f32,
+    rng: fastrand::Rng,
+}
+
+impl Default for Randomizer {
+    fn default() -> Self {
+        Self {
+            locked: [false; NUM_PARAMS],
+            amount: 0.1,
+            rng: fastrand::Rng::new(),
+        }
+    }
+}
+
+impl Randomizer {
+    pub fn is_locked(&self, param: Param) -> bool {
+        self.locked[param as usize]
+    }
+
+    pub fn set_locked(&mut self, param: Param, locked: bool) {
+        self.locked[param as usize] = locked;
+    }
+
+    fn random_choice(&mut self, descriptor: &ParamDescriptor, (low, high): (f32, f32)) -> f32 {
+        let low = descriptor.from_normalized(low) as usize;
+        let high = descriptor.from_normalized(high) as usize;
+        self.rng.usize(low..=high) as f32
+    }
+
+    fn random_value(&mut self, descriptor: &ParamDescriptor, bounds: (f32, f32)) -> f32 {
+        if descriptor.is_choice() {
+            self.random_choice(descriptor, bounds)
+        } else {
+            let (low, high) = bounds;
+            descriptor.from_normalized(low + self.rng.f32() * (high - low))
+        }
+    }
+
+    fn mutated_value(
+        &mut self,
+        descriptor: &ParamDescriptor,
+        bounds: (f32, f32),
+        value: f32,
+    ) -> f32 {
+        if descriptor.is_choice() {
+            // jumping to another choice is a big change, so do it rarely
+            if self.rng.f32() < self.amount / 2. {
+                self.random_choice(descriptor, bounds)
+            } else {
+                value
+            }
+        } else if self.amount > 0. {
+            let normalized = descriptor.to_normalized(value);
+            // don't pull values the user put outside the safe range back into it
+            let (low, high) = (bounds.0.min(normalized), bounds.1.max(normalized));
+            let offset = (self.rng.f32() * 2. - 1.) * self.amount;
+            descriptor.from_normalized((normalized + offset).clamp(low, high))
+        } else {
+            value
+        }
+    }
+
+    /// send the changes as a single undo step
+    fn apply(
+        &mut self,
+        params: &Params,
+        sender: &MessageSender,
+        history: &mut History,
+        mut new_value: impl FnMut(&mut Self, &ParamDescriptor, (f32, f32), f32) -> f32,
+    ) {
+        history.begin();
+        for param in Param::ALL {
+            let descriptor = param.descriptor();
+            let bounds = match random_bounds(descriptor) {
+                Some(bounds) if !self.is_locked(param) => bounds,
+                _ => continue,
+            };
+            let value = params.get(param);
+            let new = new_value(self, descriptor, bounds, value);
+            if new != value {
+                if let Err(e) = sender.try_send(Message::SetParam(param, new)) {
+                    warn!("error sending param change {}", e);
+                }
+                history.record(param, value, new);
+            }
+        }
+        history.end();
+    }
+
+    /// a new sound with random values for all unlocked parameters
+    pub fn randomize(&mut self, params: &Params, sender: &MessageSender, history: &mut History) {
+        self.apply(params, sender, history, |r, descriptor, bounds, _| {
+            r.random_value(descriptor, bounds)
+        });
+    }
+
+    /// move the unlocked parameters randomly by up to `amount`
+    pub fn mutate(&mut self, params: &Params, sender: &MessageSender, history: &mut History) {
+        self.apply(params, sender, history, Self::mutated_value);
+    }
+}
+
+pub fn randomizer_gui(
+    ui: &mut Ui,
+    randomizer: &mut Randomizer,
+    params: &Params,
+    sender: &MessageSender,
+    history: &mut History,
+) {
+    ui.horizontal(|ui| {
+        if ui.button("randomize").clicked() {
+            randomizer.randomize(params, sender, history);
+        }
+        if ui.button("mutate").clicked() {
+            randomizer.mutate(params, sender, history);
+        }
+        ui.add(egui::Slider::new(&mut randomizer.amount, 0f32..=1f32).text("amount"));
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::Randomizer;
+    use crate::{History, Message, Param, Params, Randomize};
+    use crossbeam::channel;
+
+    fn randomizer() -> Randomizer {
+        Randomizer {
+            rng: fastrand::Rng::with_seed(1),
+            ..Randomizer::default()
+        }
+    }
+
+    #[test]
+    fn randomize_within_bounds() {
+        let (tx, rx) = channel::unbounded();
+        let params = Params::default();
+        let mut history = History::default();
+        let mut randomizer = randomizer();
+        randomizer.set_locked(Param::Chaoticity, true);
+        for _ in 0..20 {
+            randomizer.randomize(&params, &tx, &mut history);
+        }
+        for message in rx.try_iter() {
+            let (param, value) = match message {
+                Message::SetParam(param, value) => (param, value),
+                _ => panic!("unexpected message"),
+            };
+            let descriptor = param.descriptor();
+            assert_ne!(param, Param::Chaoticity);
+            assert_eq!(descriptor.clamp(value), value);
+            match &descriptor.random {
+                Randomize::Never => panic!("{} was randomized", descriptor.id),
+                Randomize::Full => {}
+                Randomize::Within(range) => assert!(range.contains(&value), "{}", descriptor.id),
+            }
+        }
+        assert!(history.can_undo());
+    }
+
+    #[test]
+    fn mutate_amount() {
+        let (tx, rx) = channel::unbounded();
+        let params = Params::default();
+        let mut history = History::default();
+        let mut randomizer = randomizer();
+        randomizer.amount = 0.;
+        randomizer.mutate(&params, &tx, &mut history);
+        assert!(rx.try_recv().is_err());
+        randomizer.amount = 0.1;
+        randomizer.mutate(&params, &tx, &mut history);
+        for message in rx.try_iter() {
+            if let Message::SetParam(param, value) = message {
+                let descriptor = param.descriptor();
+                let moved =
+                    descriptor.to_normalized(value) - descriptor.to_normalized(params.get(param));
+                assert!(descriptor.is_choice() || moved.abs() <= 0.1 + 1e-4);
+            }
+        }
+    }
+}
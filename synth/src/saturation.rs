@@ -0,0 +1,35 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Saturation {
+    Tanh,
+    /// cubic soft clipper
+    Cubic,
+    /// folds the signal back when it exceeds the -1..=1 range
+    Foldback,
+    HardClip,
+}
+
+impl Saturation {
+    pub const ALL: [Saturation; 4] = [
+        Saturation::Tanh,
+        Saturation::Cubic,
+        Saturation::Foldback,
+        Saturation::HardClip,
+    ];
+
+    /// output is always in the range -1..=1
+    pub fn run(self, x: f32) -> f32 {
+        match self {
+            Saturation::Tanh => x.tanh(),
+            Saturation::Cubic => {
+                let x = x.clamp(-1., 1.);
+                1.5 * x - 0.5 * x.powi(3)
+            }
+            Saturation::Foldback => 1. - ((x + 1.).rem_euclid(4.) - 2.).abs(),
+            Saturation::HardClip => x.clamp(-1., 1.),
+        }
+    }
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.)
+}
@@ -1,8 +1,43 @@
-use glam::Vec4;
+use glam::{vec2, Vec2, Vec4};
+use std::f32::consts::PI;
 
 use crate::dbg_gui::dbg_value;
 use crate::pendulum::Pendulum;
 
+/// observables of the pendulum state that can be used as audio signals
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tap {
+    Angle1,
+    Angle2,
+    TipX,
+    TipY,
+    AngularVelocity1,
+    AngularVelocity2,
+    Momentum1,
+    Momentum2,
+    KineticEnergy,
+}
+
+pub const NUM_TAPS: usize = Tap::ALL.len();
+
+impl Tap {
+    pub const ALL: [Tap; 9] = [
+        Tap::Angle1,
+        Tap::Angle2,
+        Tap::TipX,
+        Tap::TipY,
+        Tap::AngularVelocity1,
+        Tap::AngularVelocity2,
+        Tap::Momentum1,
+        Tap::Momentum2,
+        Tap::KineticEnergy,
+    ];
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2. * PI) - PI
+}
+
 #[derive(Clone)]
 pub struct Simulator {
     pub pendulum: Pendulum,
@@ -28,6 +63,89 @@ impl Simulator {
         tip / (pendulum.length.x + pendulum.length.y)
     }
 
+    /// horizontal position of the first arm's bob, normalized by the first arm's length
+    pub fn get_normalized_arm_x(&self) -> f32 {
+        self.pendulum.t_pt.x.sin()
+    }
+
+    /// height of the tip above its rest position, normalized by the total length.
+    /// is in the range 0..=2
+    pub fn get_normalized_y(&self) -> f32 {
+        let Self { pendulum, .. } = self;
+        let height = (1. - pendulum.t_pt.x.cos()) * pendulum.length.x
+            + (1. - pendulum.t_pt.y.cos()) * pendulum.length.y;
+        height / (pendulum.length.x + pendulum.length.y)
+    }
+
+    /// horizontal displacement caused by each arm's angle
+    pub fn get_angle_sines(&self) -> Vec2 {
+        vec2(self.pendulum.t_pt.x.sin(), self.pendulum.t_pt.y.sin())
+    }
+
+    /// angular frequency of a simple pendulum with the same total length.
+    /// used to bring velocities and momenta into roughly the same range as the angles
+    fn natural_angular_freq(&self) -> f32 {
+        let Pendulum { g, length, .. } = self.pendulum;
+        (g / (length.x + length.y)).sqrt()
+    }
+
+    fn get_kinetic_energy(&self) -> f32 {
+        let Pendulum {
+            mass, length, t_pt, ..
+        } = self.pendulum;
+        let thetadiff = t_pt.x - t_pt.y;
+        (mass.y * length.y.powi(2) * t_pt.z.powi(2)
+            + (mass.x + mass.y) * length.x.powi(2) * t_pt.w.powi(2)
+            - 2. * mass.y * length.x * length.y * t_pt.z * t_pt.w * thetadiff.cos())
+            / (2.
+                * mass.y
+                * length.x.powi(2)
+                * length.y.powi(2)
+                * (mass.x + mass.y * thetadiff.sin().powi(2)))
+    }
+
+    /// value of an observable, 0 with the pendulum at rest and scaled to roughly -1..=1 for typical
+    /// motion. the tip height and kinetic energy never go below 0, and reach up to 2
+    pub fn get_tap(&self, tap: Tap) -> f32 {
+        let Pendulum {
+            g,
+            mass,
+            length,
+            t_pt,
+        } = self.pendulum;
+        let total_length = length.x + length.y;
+        match tap {
+            Tap::Angle1 => wrap_angle(t_pt.x) / PI,
+            Tap::Angle2 => wrap_angle(t_pt.y) / PI,
+            Tap::TipX => self.get_normalized_x(),
+            Tap::TipY => self.get_normalized_y(),
+            Tap::AngularVelocity1 | Tap::AngularVelocity2 => {
+                let velocity = self.pendulum.derivatives(&t_pt);
+                let velocity = if tap == Tap::AngularVelocity1 {
+                    velocity.x
+                } else {
+                    velocity.y
+                };
+                velocity / self.natural_angular_freq() / PI
+            }
+            Tap::Momentum1 | Tap::Momentum2 => {
+                let momentum = if tap == Tap::Momentum1 {
+                    t_pt.z
+                } else {
+                    t_pt.w
+                };
+                momentum
+                    / ((mass.x + mass.y) * total_length.powi(2) * self.natural_angular_freq())
+                    / PI
+            }
+            Tap::KineticEnergy => {
+                // relative to the energy needed to hold the pendulum horizontally, which makes it
+                // 0..=2 with the energies the synth uses
+                self.get_kinetic_energy() / (g * ((mass.x + mass.y) * length.x + mass.y * length.y))
+            }
+        }
+    }
+
     pub fn update(&mut self, elapsed: f32, energy: f32, p: f32) {
         debug_assert!(energy >= 0.);
         debug_assert!((0. ..=1.).contains(&p));
@@ -0,0 +1,191 @@
+// parameter values as seen by the audio thread. changes glide to their new value over the
+// descriptor's smoothing time instead of jumping, which would click
+
+use crate::{
+    envelope::Adsr,
+    filter::FilterSettings,
+    lfo::{LfoSettings, LFO_PARAMS},
+    mod_matrix::{self, ModSources, MOD_SLOT_PARAMS},
+    FilterMode, LfoDestination, LfoMode, LfoShape, LfoSync, ModCurve, ModSource, Param, Params,
+    NUM_PARAMS,
+};
+
+#[derive(Clone)]
+pub(crate) struct SmoothedParams {
+    /// indexed by `Param`
+    values: [f32; NUM_PARAMS],
+    /// the values with the modulation matrix applied
+    modulated: [f32; NUM_PARAMS],
+    targets: [f32; NUM_PARAMS],
+    /// fraction of the remaining distance covered each sample. 1 for unsmoothed parameters
+    coefficients: [f32; NUM_PARAMS],
+}
+
+impl Default for SmoothedParams {
+    fn default() -> Self {
+        let defaults = Param::ALL.map(|param| param.descriptor().default);
+        Self {
+            values: defaults,
+            modulated: defaults,
+            targets: defaults,
+            coefficients: [1.; NUM_PARAMS],
+        }
+    }
+}
+
+impl SmoothedParams {
+    /// read the current values to move towards. call once per block
+    pub fn set_targets(&mut self, params: &Params, sample_rate: u32) {
+        for param in Param::ALL {
+            let descriptor = param.descriptor();
+            self.targets[param as usize] = params.get(param);
+            // choices can't be in between
+            self.coefficients[param as usize] =
+                if descriptor.is_choice() || descriptor.smoothing <= 0. {
+                    1.
+                } else {
+                    1. - (-1. / (descriptor.smoothing * sample_rate as f32)).exp()
+                };
+        }
+    }
+
+    /// advance one sample. the modulation is kept until the next `modulate`
+    pub fn update(&mut self) {
+        for ((value, target), coefficient) in self
+            .values
+            .iter_mut()
+            .zip(self.targets)
+            .zip(self.coefficients)
+        {
+            *value += (target - *value) * coefficient;
+        }
+    }
+
+    /// apply the modulation matrix to the current values
+    pub fn modulate(&mut self, sources: &ModSources) {
+        // in normalized units
+        let mut offsets = [0f32; NUM_PARAMS];
+        for slot in &MOD_SLOT_PARAMS {
+            let source = self.get_choice(slot.source, &ModSource::ALL);
+            let destination = match mod_matrix::destination(self.values[slot.destination as usize])
+            {
+                Some(destination) if source != ModSource::None => destination,
+                _ => continue,
+            };
+            let curve = self.get_choice(slot.curve, &ModCurve::ALL);
+            offsets[destination as usize] +=
+                self.values[slot.amount as usize] * curve.apply(sources.get(source));
+        }
+        for ((modulated, &value), (param, offset)) in self
+            .modulated
+            .iter_mut()
+            .zip(&self.values)
+            .zip(Param::ALL.into_iter().zip(offsets))
+        {
+            *modulated = if offset == 0. {
+                value
+            } else {
+                let descriptor = param.descriptor();
+                descriptor.from_normalized(descriptor.to_normalized(value) + offset)
+            };
+        }
+    }
+
+    /// the value with modulation
+    pub fn get(&self, param: Param) -> f32 {
+        self.modulated[param as usize]
+    }
+
+    /// `choices` must be in the same order as the descriptor's choice names
+    pub fn get_choice<T: Copy>(&self, param: Param, choices: &[T]) -> T {
+        debug_assert_eq!(choices.len(), param.descriptor().choices.len());
+        choices[(self.get(param) as usize).min(choices.len() - 1)]
+    }
+
+    pub fn get_filter_settings(&self) -> FilterSettings {
+        FilterSettings {
+            mode: self.get_choice(Param::FilterMode, &FilterMode::ALL),
+            cutoff: self.get(Param::FilterCutoff),
+            resonance: self.get(Param::FilterResonance),
+            envelope_amount: self.get(Param::FilterEnvelopeAmount),
+            envelope: Adsr {
+                attack: self.get(Param::FilterAttack),
+                decay: self.get(Param::FilterDecay),
+                sustain: self.get(Param::FilterSustain),
+                release: self.get(Param::FilterRelease),
+            },
+            key_tracking: self.get(Param::FilterKeyTracking),
+            velocity_amount: self.get(Param::FilterVelocity),
+        }
+    }
+
+    pub fn get_lfo_settings(&self, index: usize) -> LfoSettings {
+        let params = &LFO_PARAMS[index];
+        LfoSettings {
+            shape: self.get_choice(params.shape, &LfoShape::ALL),
+            rate: self.get(params.rate),
+            sync: self.get_choice(params.sync, &LfoSync::ALL),
+            phase: self.get(params.phase),
+            mode: self.get_choice(params.mode, &LfoMode::ALL),
+            destination: self.get_choice(params.destination, &LfoDestination::ALL),
+            depth: self.get(params.depth),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SmoothedParams;
+    use crate::{mod_matrix::ModSources, Param, Params};
+
+    fn step(smoothed: &mut SmoothedParams) {
+        smoothed.update();
+        smoothed.modulate(&ModSources::default());
+    }
+
+    #[test]
+    fn glide() {
+        let params = Params::default();
+        let mut smoothed = SmoothedParams::default();
+        params.set(Param::Chaoticity, 1.);
+        params.set(Param::StereoMode, 3.);
+        let sample_rate = 48000;
+        smoothed.set_targets(&params, sample_rate);
+        step(&mut smoothed);
+        assert_eq!(smoothed.get(Param::StereoMode), 3.);
+        let start = Param::Chaoticity.descriptor().default;
+        let mut previous = smoothed.get(Param::Chaoticity);
+        assert!(previous > start && previous < 1.);
+        let time_constant =
+            (Param::Chaoticity.descriptor().smoothing * sample_rate as f32) as usize;
+        for _ in 1..time_constant {
+            step(&mut smoothed);
+            let value = smoothed.get(Param::Chaoticity);
+            assert!(value > previous);
+            previous = value;
+        }
+        // about 63% of the way after one time constant
+        let progress = (previous - start) / (1. - start);
+        assert!((progress - 0.63).abs() < 0.02, "{progress}");
+        for _ in 0..10 * time_constant {
+            step(&mut smoothed);
+        }
+        assert!((smoothed.get(Param::Chaoticity) - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn mod_wheel_to_chaoticity() {
+        let mut smoothed = SmoothedParams::default();
+        let sources = ModSources {
+            mod_wheel: 0.25,
+            ..ModSources::default()
+        };
+        smoothed.modulate(&sources);
+        let descriptor = Param::Chaoticity.descriptor();
+        let offset = descriptor.to_normalized(smoothed.get(Param::Chaoticity))
+            - descriptor.to_normalized(descriptor.default);
+        assert!((offset - 0.25).abs() < 1e-4);
+        smoothed.modulate(&ModSources::default());
+        assert_eq!(smoothed.get(Param::Chaoticity), descriptor.default);
+    }
+}
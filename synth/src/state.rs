@@ -0,0 +1,66 @@
+// snapshot of the synth state. stored as a `Patch` wherever it has to be serialized
+
+use log::warn;
+
+use crate::{Message, MessageSender, Param, Params, Tuning, NUM_PARAMS};
+
+/// everything needed to restore a sound
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    /// indexed by `Param`
+    pub values: [f32; NUM_PARAMS],
+    pub tuning: Tuning,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            values: Param::ALL.map(|param| param.descriptor().default),
+            tuning: Tuning::default(),
+        }
+    }
+}
+
+impl State {
+    pub fn capture(params: &Params) -> Self {
+        Self {
+            values: Param::ALL.map(|param| params.get(param)),
+            tuning: params.get_tuning(),
+        }
+    }
+
+    /// takes effect immediately, so a `capture` right after gets the same state back. for loading
+    /// sounds, where hosts read the result back right away
+    pub fn apply(&self, params: &Params) {
+        params.set_tuning(self.tuning.clone());
+        for param in Param::ALL {
+            params.set(param, self.values[param as usize]);
+        }
+    }
+
+    /// for switching sounds from the gui. the values reach the synth as `Message::SetParam`, in
+    /// order with the edits sent before them
+    pub fn send(&self, params: &Params, sender: &MessageSender) {
+        params.set_tuning(self.tuning.clone());
+        for param in Param::ALL {
+            if let Err(e) = sender.try_send(Message::SetParam(param, self.values[param as usize])) {
+                warn!("error sending param change {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::State;
+    use crate::{Param, Params};
+
+    #[test]
+    fn apply_is_immediate() {
+        let params = Params::default();
+        let mut state = State::default();
+        state.values[Param::Release as usize] = 0.7;
+        state.apply(&params);
+        assert_eq!(State::capture(&params), state);
+    }
+}
@@ -0,0 +1,329 @@
+// scala scale (.scl) and keyboard mapping (.kbm) support
+// see https://www.huygens-fokker.org/scala/scl_format.html and https://www.huygens-fokker.org/scala/help.htm#mappings
+
+use std::{error::Error, fmt};
+
+pub const NUM_NOTES: usize = 128;
+/// frequency all tables are normalized to. the master tune is applied relative to this
+pub const REFERENCE_FREQ: f32 = 440f32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+/// iterate over the non-comment lines of a scala file together with their (1-based) line numbers
+fn content_lines(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.starts_with('!'))
+}
+
+fn parse_error(line: usize, message: impl Into<String>) -> ParseError {
+    ParseError {
+        line,
+        message: message.into(),
+    }
+}
+
+fn parse_first_word<T: std::str::FromStr>(
+    line: (usize, &str),
+    what: &str,
+) -> Result<T, ParseError> {
+    let (num, line) = line;
+    line.split_whitespace()
+        .next()
+        .and_then(|word| word.parse().ok())
+        .ok_or_else(|| parse_error(num, format!("expected {what}")))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+    pub description: String,
+    /// frequency ratios of each degree relative to the first (implicit 1/1) degree.
+    /// the last entry is the period of the scale, usually 2/1
+    pub ratios: Vec<f64>,
+    source: String,
+}
+
+impl Scale {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut lines = content_lines(source);
+        let description = lines
+            .next()
+            .map(|(_, line)| line.trim().to_string())
+            .ok_or_else(|| parse_error(1, "missing description"))?;
+        let count_line = lines
+            .next()
+            .ok_or_else(|| parse_error(2, "missing note count"))?;
+        let count: usize = parse_first_word(count_line, "note count")?;
+        let mut ratios = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (num, line) = lines
+                .next()
+                .ok_or_else(|| parse_error(count_line.0, format!("expected {count} pitches")))?;
+            let word = line
+                .split_whitespace()
+                .next()
+                .ok_or_else(|| parse_error(num, "empty pitch"))?;
+            let ratio = if word.contains('.') {
+                let cents: f64 = word
+                    .parse()
+                    .map_err(|_| parse_error(num, format!("invalid cents value {word}")))?;
+                2f64.powf(cents / 1200.)
+            } else {
+                let (numerator, denominator) = word.split_once('/').unwrap_or((word, "1"));
+                let numerator: u64 = numerator
+                    .parse()
+                    .map_err(|_| parse_error(num, format!("invalid ratio {word}")))?;
+                let denominator: u64 = denominator
+                    .parse()
+                    .map_err(|_| parse_error(num, format!("invalid ratio {word}")))?;
+                if denominator == 0 {
+                    return Err(parse_error(num, "zero denominator"));
+                }
+                numerator as f64 / denominator as f64
+            };
+            if ratio <= 0. {
+                return Err(parse_error(num, "pitch must be positive"));
+            }
+            ratios.push(ratio);
+        }
+        if ratios.is_empty() {
+            return Err(parse_error(count_line.0, "scale has no notes"));
+        }
+        Ok(Self {
+            description,
+            ratios,
+            source: source.to_string(),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// ratio of any scale degree, including negative ones and ones beyond the period
+    fn degree_ratio(&self, degree: i32) -> f64 {
+        let len = self.ratios.len() as i32;
+        let period = *self.ratios.last().unwrap();
+        let octave = degree.div_euclid(len);
+        let index = degree.rem_euclid(len);
+        let ratio = if index == 0 {
+            1.
+        } else {
+            self.ratios[index as usize - 1]
+        };
+        ratio * period.powi(octave)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardMapping {
+    pub first_note: i32,
+    pub last_note: i32,
+    pub middle_note: i32,
+    pub reference_note: i32,
+    pub reference_freq: f64,
+    /// scale degree to use as the formal octave. 0 means use the period of the scale
+    pub octave_degree: usize,
+    /// scale degree for each key of the repeating pattern, None for unmapped keys.
+    /// an empty mapping means a linear mapping of keys to degrees
+    pub mapping: Vec<Option<i32>>,
+    source: String,
+}
+
+impl Default for KeyboardMapping {
+    fn default() -> Self {
+        Self {
+            first_note: 0,
+            last_note: NUM_NOTES as i32 - 1,
+            middle_note: 60,
+            reference_note: 69,
+            reference_freq: REFERENCE_FREQ as f64,
+            octave_degree: 0,
+            mapping: vec![],
+            source: String::new(),
+        }
+    }
+}
+
+impl KeyboardMapping {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut lines = content_lines(source).filter(|(_, line)| !line.trim().is_empty());
+        let mut next = |what: &str| {
+            lines
+                .next()
+                .ok_or_else(|| parse_error(0, format!("missing {what}")))
+        };
+        let size: usize = parse_first_word(next("map size")?, "map size")?;
+        let first_note = parse_first_word(next("first note")?, "first note")?;
+        let last_note = parse_first_word(next("last note")?, "last note")?;
+        let middle_note = parse_first_word(next("middle note")?, "middle note")?;
+        let reference_note = parse_first_word(next("reference note")?, "reference note")?;
+        let reference_freq: f64 =
+            parse_first_word(next("reference frequency")?, "reference frequency")?;
+        let octave_degree = parse_first_word(next("octave degree")?, "octave degree")?;
+        let mut mapping = Vec::with_capacity(size);
+        for _ in 0..size {
+            // trailing entries may be left out, in which case they are unmapped
+            match lines.next() {
+                Some((num, line)) => {
+                    let word = line.split_whitespace().next().unwrap_or("x");
+                    if word == "x" {
+                        mapping.push(None);
+                    } else {
+                        mapping.push(Some(word.parse().map_err(|_| {
+                            parse_error(num, format!("invalid mapping entry {word}"))
+                        })?));
+                    }
+                }
+                None => mapping.push(None),
+            }
+        }
+        if reference_freq <= 0. {
+            return Err(parse_error(0, "reference frequency must be positive"));
+        }
+        Ok(Self {
+            first_note,
+            last_note,
+            middle_note,
+            reference_note,
+            reference_freq,
+            octave_degree,
+            mapping,
+            source: source.to_string(),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// ratio of a key relative to the middle note, or None if it is unmapped
+    fn key_ratio(&self, scale: &Scale, key: i32) -> Option<f64> {
+        let offset = key - self.middle_note;
+        if self.mapping.is_empty() {
+            return Some(scale.degree_ratio(offset));
+        }
+        let size = self.mapping.len() as i32;
+        let repeats = offset.div_euclid(size);
+        let degree = self.mapping[offset.rem_euclid(size) as usize]?;
+        let formal_octave = if self.octave_degree == 0 {
+            scale.degree_ratio(scale.ratios.len() as i32)
+        } else {
+            scale.degree_ratio(self.octave_degree as i32)
+        };
+        Some(scale.degree_ratio(degree) * formal_octave.powi(repeats))
+    }
+}
+
+/// a scale together with a keyboard mapping. defaults to 12 tone equal temperament with A4 at 440Hz
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tuning {
+    pub scale: Option<Scale>,
+    pub keyboard_mapping: Option<KeyboardMapping>,
+}
+
+impl Tuning {
+    /// frequency of each midi note, normalized to a 440Hz reference.
+    /// unmapped notes get a frequency of 0
+    pub fn frequencies(&self) -> [f32; NUM_NOTES] {
+        let equal_temperament;
+        let scale = match &self.scale {
+            Some(scale) => scale,
+            None => {
+                equal_temperament = Scale {
+                    description: String::new(),
+                    ratios: (1..=12).map(|i| 2f64.powf(i as f64 / 12.)).collect(),
+                    source: String::new(),
+                };
+                &equal_temperament
+            }
+        };
+        let default_mapping;
+        let mapping = match &self.keyboard_mapping {
+            Some(mapping) => mapping,
+            None => {
+                default_mapping = KeyboardMapping::default();
+                &default_mapping
+            }
+        };
+        // the reference note might be unmapped, in which case use a linear mapping for it
+        let reference_ratio = mapping
+            .key_ratio(scale, mapping.reference_note)
+            .unwrap_or_else(|| scale.degree_ratio(mapping.reference_note - mapping.middle_note));
+        let mut frequencies = [0f32; NUM_NOTES];
+        for (key, freq) in frequencies.iter_mut().enumerate() {
+            let key = key as i32;
+            *freq = if (mapping.first_note..=mapping.last_note).contains(&key) {
+                match mapping.key_ratio(scale, key) {
+                    Some(ratio) => (mapping.reference_freq * ratio / reference_ratio) as f32,
+                    None => 0f32,
+                }
+            } else {
+                // keys outside the mapped range are left in equal temperament
+                REFERENCE_FREQ * 2f32.powf((key - 69) as f32 / 12f32)
+            };
+        }
+        frequencies
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{KeyboardMapping, Scale, Tuning};
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{a} != {b}");
+    }
+
+    #[test]
+    fn equal_temperament() {
+        let frequencies = Tuning::default().frequencies();
+        assert_close(frequencies[69], 440.);
+        assert_close(frequencies[81], 880.);
+        assert_close(frequencies[60], 261.63);
+    }
+
+    #[test]
+    fn parse_scale() {
+        let scale =
+            Scale::parse("! meantone.scl\n!\nquarter comma meantone\n 3\n!\n 193.157\n 5/4\n 2\n")
+                .unwrap();
+        assert_eq!(scale.description, "quarter comma meantone");
+        assert_eq!(scale.ratios.len(), 3);
+        assert!((scale.ratios[1] - 1.25).abs() < 1e-9);
+        assert!((scale.ratios[2] - 2.).abs() < 1e-9);
+        assert!(Scale::parse("bad\n2\n1.5\n").is_err());
+    }
+
+    #[test]
+    fn mapped_scale() {
+        let scale = Scale::parse("just\n2\n3/2\n2/1\n").unwrap();
+        let keyboard_mapping =
+            KeyboardMapping::parse("! two keys per octave\n2\n0\n127\n60\n60\n100.0\n0\n0\nx\n")
+                .unwrap();
+        let frequencies = Tuning {
+            scale: Some(scale),
+            keyboard_mapping: Some(keyboard_mapping),
+        }
+        .frequencies();
+        assert_close(frequencies[60], 100.);
+        assert_close(frequencies[61], 0.);
+        // the pattern repeats every two keys, transposed by the period
+        assert_close(frequencies[62], 200.);
+        assert_close(frequencies[58], 50.);
+    }
+}
@@ -0,0 +1,52 @@
+/// response of the synth to how hard notes are played
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VelocityCurve {
+    Linear,
+    /// quiet playing already gets most of the range
+    Soft,
+    /// needs hard playing to reach the top of the range
+    Hard,
+    /// every note at full velocity
+    Fixed,
+}
+
+impl VelocityCurve {
+    pub const ALL: [VelocityCurve; 4] = [
+        VelocityCurve::Linear,
+        VelocityCurve::Soft,
+        VelocityCurve::Hard,
+        VelocityCurve::Fixed,
+    ];
+
+    /// `velocity` is 0..=1
+    pub fn apply(self, velocity: f32) -> f32 {
+        let velocity = velocity.clamp(0., 1.);
+        match self {
+            VelocityCurve::Linear => velocity,
+            VelocityCurve::Soft => velocity.sqrt(),
+            VelocityCurve::Hard => velocity * velocity,
+            VelocityCurve::Fixed => 1.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VelocityCurve;
+
+    #[test]
+    fn curves() {
+        for curve in VelocityCurve::ALL {
+            assert_eq!(curve.apply(1.), 1., "{curve:?}");
+            let mut previous = curve.apply(0.);
+            for i in 1..=10 {
+                let value = curve.apply(i as f32 / 10.);
+                assert!(value >= previous, "{curve:?}");
+                previous = value;
+            }
+        }
+        assert!(VelocityCurve::Soft.apply(0.5) > VelocityCurve::Linear.apply(0.5));
+        assert!(VelocityCurve::Hard.apply(0.5) < VelocityCurve::Linear.apply(0.5));
+        assert_eq!(VelocityCurve::Fixed.apply(0.), 1.);
+    }
+}
@@ -3,25 +3,33 @@ use egui_baseview::EguiWindow;
 use pistolhot_synth as synth;
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use std::sync::Arc;
-use synth::params_gui;
+use synth::{
+    compare_gui, params_gui, randomizer_gui, CompareSlots, History, MessageSender, Programs,
+    Randomizer,
+};
 use vst::editor::Editor;
 
 pub struct PistolhotEditor {
     window_handle: Option<baseview::WindowHandle>,
     params: Arc<synth::Params>,
+    programs: Arc<Programs>,
+    sender: MessageSender,
 }
 
 impl PistolhotEditor {
-    pub fn new(params: Arc<synth::Params>) -> Self {
+    pub fn new(params: Arc<synth::Params>, programs: Arc<Programs>, sender: MessageSender) -> Self {
         Self {
             window_handle: None,
             params,
+            programs,
+            sender,
         }
     }
 }
 
-const WINDOW_WIDTH: i32 = 300;
-const WINDOW_HEIGHT: i32 = 200;
+// wide enough for the parameter sliders, the rest scrolls
+const WINDOW_WIDTH: i32 = 500;
+const WINDOW_HEIGHT: i32 = 700;
 
 impl Editor for PistolhotEditor {
     fn size(&self) -> (i32, i32) {
@@ -45,18 +53,48 @@ impl Editor for PistolhotEditor {
             gl_config: Some(baseview::gl::GlConfig::default()),
         };
         let params = self.params.clone();
+        let programs = self.programs.clone();
+        let sender = self.sender.clone();
         self.window_handle = EguiWindow::open_parented(
             &VstParent(parent),
             settings,
-            (),
+            (
+                History::default(),
+                CompareSlots::default(),
+                Randomizer::default(),
+            ),
             // build
-            |_ctx: &egui::Context, _queue: &mut egui_baseview::Queue, _state: &mut ()| {},
+            |_ctx: &egui::Context,
+             _queue: &mut egui_baseview::Queue,
+             _state: &mut (History, CompareSlots, Randomizer)| {},
             // update
-            move |egui_ctx: &egui::Context, _queue: &mut egui_baseview::Queue, _state: &mut ()| {
+            move |egui_ctx: &egui::Context,
+                  _queue: &mut egui_baseview::Queue,
+                  (history, compare, randomizer): &mut (History, CompareSlots, Randomizer)| {
+                // the tuning of program changes from midi
+                programs.update(&params);
                 egui::CentralPanel::default().show(egui_ctx, |ui| {
                     ui.heading("Pistolhot");
-                    ui.group(|ui| {
-                        params_gui(ui, &params);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(history.can_undo(), egui::Button::new("undo"))
+                            .clicked()
+                        {
+                            history.undo(&sender);
+                        }
+                        if ui
+                            .add_enabled(history.can_redo(), egui::Button::new("redo"))
+                            .clicked()
+                        {
+                            history.redo(&sender);
+                        }
+                    });
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.group(|ui| {
+                            compare_gui(ui, compare, &params, &sender);
+                            randomizer_gui(ui, randomizer, &params, &sender, history);
+                            params_gui(ui, &params, &sender, history, randomizer);
+                        });
                     });
                 });
             },
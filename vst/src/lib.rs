@@ -1,15 +1,20 @@
 /*
 TODO handle hidpi on windows
 */
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
 
-use crossbeam::atomic::AtomicCell;
-use log::info;
+use crossbeam::{atomic::AtomicCell, channel};
+use log::{info, warn};
 use once_cell::sync::OnceCell;
 use pistolhot_synth as synth;
-use synth::SynthPlayer;
+use synth::{Message, Param, Patch, Programs, SynthPlayer};
 use vst::{
+    api::TimeInfoFlags,
     editor::Editor,
+    host::Host,
     plugin::{Category, HostCallback, Info, Plugin},
     plugin_main,
 };
@@ -18,9 +23,14 @@ mod editor;
 use editor::PistolhotEditor;
 
 struct Data {
+    host: HostCallback,
     sample_rate: u32,
     synth: synth::Synth,
-    midi_sender: crossbeam::channel::Sender<wmidi::MidiMessage<'static>>,
+    /// for the editor
+    sender: synth::MessageSender,
+    params: Arc<Params>,
+    /// from `Params::set_parameter`
+    automation: channel::Receiver<(usize, Param, f32)>,
 }
 
 #[derive(Default)]
@@ -58,17 +68,62 @@ fn init_logging() {
     info!("{}", env!("CARGO_PKG_VERSION"));
 }
 
+/// the standalone app's user presets, sorted by name like in its browser
+fn read_user_presets() -> Vec<Patch> {
+    let folder = match dirs::data_dir() {
+        Some(dir) => dir.join("org.deepness.pistolhot").join("presets"),
+        None => return vec![],
+    };
+    // there is no folder until the app has saved a preset
+    let entries = match std::fs::read_dir(&folder) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut patches: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension() == Some("toml".as_ref()))
+        .filter_map(|path| {
+            match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| Patch::from_toml(&source).map_err(|e| e.to_string()))
+            {
+                Ok(patch) => Some(patch),
+                Err(e) => {
+                    warn!("error reading preset {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+    patches.sort_by(|a, b| a.name.cmp(&b.name));
+    patches
+}
+
 impl Plugin for PistolhotVst {
-    fn new(_host: HostCallback) -> Self {
+    fn new(host: HostCallback) -> Self {
         init_logging();
 
-        let (midi_sender, midi_receiver) = crossbeam::channel::bounded(1024);
-        let synth = synth::Synth::new(midi_receiver);
+        // host midi goes to the synth through schedule, the editor's edits through the channel
+        let (sender, receiver) = channel::bounded(synth::CHANNEL_CAPACITY);
+        let synth = synth::Synth::new(receiver);
+        // after the factory programs
+        synth.get_programs().extend(&read_user_presets());
+        let (automation_sender, automation) = channel::bounded(synth::CHANNEL_CAPACITY);
+        let params = Arc::new(Params {
+            params: synth.get_params(),
+            programs: synth.get_programs(),
+            position: synth.get_position(),
+            automation: automation_sender,
+            pending: std::array::from_fn(|_| AtomicU32::new(NOT_PENDING)),
+        });
         let sample_rate = 44100;
         Self(Some(Data {
+            host,
             sample_rate,
             synth,
-            midi_sender,
+            sender,
+            params,
+            automation,
         }))
     }
 
@@ -80,6 +135,11 @@ impl Plugin for PistolhotVst {
             inputs: 0,
             outputs: 2,
             parameters: Params::NUM_PARAMS,
+            presets: self
+                .0
+                .as_ref()
+                .map_or(0, |data| data.synth.get_programs().len()) as i32,
+            preset_chunks: true,
             ..Default::default()
         }
     }
@@ -97,7 +157,7 @@ impl Plugin for PistolhotVst {
     }
 
     fn process_events(&mut self, events: &vst::api::Events) {
-        let sender = &mut self.get_mut_data().midi_sender;
+        let synth = &mut self.get_mut_data().synth;
         for e in events.events() {
             if let vst::event::Event::Midi(me) = e {
                 // TODO don't unwrap. log
@@ -105,7 +165,8 @@ impl Plugin for PistolhotVst {
                     .unwrap()
                     .drop_unowned_sysex()
                 {
-                    sender.send(m).unwrap();
+                    // the events are for the next process call, at their offsets into it
+                    synth.schedule(me.delta_frames.max(0) as usize, m.into());
                 }
             }
         }
@@ -113,6 +174,18 @@ impl Plugin for PistolhotVst {
 
     fn process(&mut self, buffer: &mut vst::buffer::AudioBuffer<f32>) {
         let data = self.get_mut_data();
+        let tempo_valid = TimeInfoFlags::TEMPO_VALID.bits();
+        if let Some(time_info) = data.host.get_time_info(tempo_valid) {
+            if time_info.flags & tempo_valid != 0 {
+                data.synth.set_tempo(time_info.tempo as f32);
+            }
+        }
+        // host automation, see `Params::set_parameter`
+        let mut scheduled = [NOT_PENDING; synth::NUM_PARAMS];
+        while let Ok((frame, param, value)) = data.automation.try_recv() {
+            data.synth.schedule(frame, Message::SetParam(param, value));
+            scheduled[param as usize] = value.to_bits();
+        }
         // TODO keep scratch buffer to avoid allocations, or change the synthplayer trait to handle non-interleaved channels
         let num_samples = buffer.samples();
         let (_, mut outputs) = buffer.split();
@@ -126,49 +199,168 @@ impl Plugin for PistolhotVst {
                 *b = interleaved[channels * sampleidx + channel];
             }
         }
+        data.params.caught_up(&scheduled);
     }
 
     fn get_parameter_object(&mut self) -> Arc<dyn vst::plugin::PluginParameters> {
-        Arc::new(Params {
-            params: self.get_mut_data().synth.get_params(),
-        })
+        self.get_mut_data().params.clone()
     }
 
     fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
         let data = self.get_mut_data();
-        let editor = PistolhotEditor::new(data.synth.get_params());
+        let editor = PistolhotEditor::new(
+            data.synth.get_params(),
+            data.synth.get_programs(),
+            data.sender.clone(),
+        );
         Some(Box::new(editor))
     }
 }
 
+/// marks a parameter the host hasn't set since the synth last caught up. a nan, so no value
+/// the host sets can be mistaken for it
+const NOT_PENDING: u32 = u32::MAX;
+
 struct Params {
     params: Arc<synth::Params>,
+    programs: Arc<Programs>,
+    /// of the synth, see `Synth::get_position`
+    position: Arc<AtomicCell<usize>>,
+    automation: channel::Sender<(usize, Param, f32)>,
+    /// bits of what the host set but the synth hasn't applied yet, so it can be read back right
+    /// away. indexed by `Param`
+    pending: [AtomicU32; synth::NUM_PARAMS],
 }
 
 impl Params {
-    const NUM_PARAMS: i32 = 1;
+    const NUM_PARAMS: i32 = synth::NUM_PARAMS as i32;
+
+    fn param_id(index: i32) -> Option<Param> {
+        Param::ALL.get(usize::try_from(index).ok()?).copied()
+    }
 
-    fn param_ref(&self, index: i32) -> &AtomicCell<f32> {
-        match index {
-            0 => &self.params.chaoticity,
-            _ => panic!("unknown param"),
+    /// the host's value until the synth has applied it
+    fn get(&self, param: Param) -> f32 {
+        match self.pending[param as usize].load(Ordering::Relaxed) {
+            NOT_PENDING => self.params.get(param),
+            bits => f32::from_bits(bits),
+        }
+    }
+
+    /// after a block, with the last value of each parameter that was scheduled for it
+    fn caught_up(&self, scheduled: &[u32; synth::NUM_PARAMS]) {
+        for (pending, &bits) in self.pending.iter().zip(scheduled) {
+            if bits != NOT_PENDING {
+                // unless the host has set it again in the meantime
+                let _ = pending.compare_exchange(
+                    bits,
+                    NOT_PENDING,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                );
+            }
+        }
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        self.params.to_chunk()
+    }
+
+    fn load_state(&self, data: &[u8]) {
+        if let Err(e) = self.params.from_chunk(data) {
+            warn!("error loading state {}", e);
         }
     }
 }
 
+/// the host sees every parameter as 0..=1, mapped to its real range by the descriptor
 impl vst::plugin::PluginParameters for Params {
     fn get_parameter(&self, index: i32) -> f32 {
-        self.param_ref(index).load()
+        Self::param_id(index).map_or(0., |param| {
+            param.descriptor().to_normalized(self.get(param))
+        })
     }
 
+    /// vst2 gives parameter changes no offset into the block. they are applied in the next block,
+    /// at the frame the synth was at when they arrived, so automation sent while it renders keeps
+    /// its timing. changes from between blocks land at the start, and are smoothed from there
     fn set_parameter(&self, index: i32, value: f32) {
-        self.param_ref(index).store(value)
+        if let Some(param) = Self::param_id(index) {
+            let value = param.descriptor().from_normalized(value);
+            self.pending[param as usize].store(value.to_bits(), Ordering::Relaxed);
+            if let Err(e) = self
+                .automation
+                .try_send((self.position.load(), param, value))
+            {
+                warn!("error sending automation {}", e);
+            }
+        }
     }
 
     fn get_parameter_name(&self, index: i32) -> String {
-        match index {
-            0 => "chaoticity".to_string(),
-            _ => "".to_string(),
+        Self::param_id(index).map_or_else(String::new, |param| param.descriptor().name.to_string())
+    }
+
+    fn get_parameter_text(&self, index: i32) -> String {
+        Self::param_id(index).map_or_else(String::new, |param| {
+            param.descriptor().format_value(self.get(param))
+        })
+    }
+
+    fn get_parameter_label(&self, index: i32) -> String {
+        Self::param_id(index).map_or_else(String::new, |param| param.descriptor().unit.to_string())
+    }
+
+    fn can_be_automated(&self, index: i32) -> bool {
+        Self::param_id(index).is_some()
+    }
+
+    fn change_preset(&self, preset: i32) {
+        if let Ok(index) = usize::try_from(preset) {
+            self.programs.select(index, &self.params);
+        }
+    }
+
+    fn get_preset_num(&self) -> i32 {
+        self.programs.current() as i32
+    }
+
+    fn set_preset_name(&self, name: String) {
+        self.programs.set_name(self.programs.current(), name);
+    }
+
+    fn get_preset_name(&self, preset: i32) -> String {
+        usize::try_from(preset)
+            .ok()
+            .and_then(|index| self.programs.name(index))
+            .unwrap_or_default()
+    }
+
+    /// the current program only
+    fn get_preset_data(&self) -> Vec<u8> {
+        self.save_state()
+    }
+
+    /// every program
+    fn get_bank_data(&self) -> Vec<u8> {
+        self.programs.to_toml(&self.params).into_bytes()
+    }
+
+    fn load_preset_data(&self, data: &[u8]) {
+        self.load_state(data);
+    }
+
+    fn load_bank_data(&self, data: &[u8]) {
+        let data = match std::str::from_utf8(data) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("error loading bank {}", e);
+                return;
+            }
+        };
+        match self.programs.load_toml(data) {
+            Ok(state) => state.apply(&self.params),
+            Err(e) => warn!("error loading bank {}", e),
         }
     }
 }